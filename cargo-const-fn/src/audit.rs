@@ -0,0 +1,310 @@
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+// `cargo const-fn audit`: reports every `#[const_fn]` function and its condition.
+
+use std::{
+    fmt::Write as _,
+    path::{Path, PathBuf},
+};
+
+use const_fn_core::Condition;
+use serde_json::{Value, json};
+
+use crate::{
+    metadata::RustVersion,
+    report::{self, Format},
+    scan,
+};
+
+const USAGE: &str = "\
+Reports every #[const_fn] function in the workspace and its condition
+
+USAGE:
+    cargo const-fn audit [OPTIONS] [PATH]
+
+ARGS:
+    <PATH>    Directory of the package or workspace to scan [default: .]
+
+OPTIONS:
+        --format <FORMAT>          Output format: table or json [default: table]
+        --rustc-version <VERSION>  Also report whether each function is const on the
+                                   given compiler version (e.g., 1.61 or 1.61-nightly)
+    -h, --help                     Print help information
+";
+
+// The compiler specified by `--rustc-version`.
+struct Target {
+    version: RustVersion,
+    nightly: bool,
+}
+
+struct Entry {
+    package: String,
+    file: String,
+    line: usize,
+    name: String,
+    vis: String,
+    // The arguments of the attribute as written.
+    args: String,
+    // The predicate if the attribute is behind `cfg_attr`.
+    cfg_attr: Option<String>,
+    condition: Result<Condition, String>,
+    // Whether the condition always holds on the `rust-version` of the package,
+    // or `None` if it cannot be determined.
+    redundant: Option<bool>,
+    // Whether the function is `const` on the target, or `None` if it depends on
+    // cfgs or features.
+    const_on_target: Option<bool>,
+}
+
+pub(crate) fn run(args: impl Iterator<Item = String>) -> Result<(), String> {
+    let mut path = None;
+    let mut format = Format::Table;
+    let mut target = None;
+    let mut args = args;
+    while let Some(arg) = args.next() {
+        match &*arg {
+            "--format" => {
+                format = Format::parse(&crate::value(&mut args, &arg)?)?;
+            }
+            "--rustc-version" => {
+                let v = crate::value(&mut args, &arg)?;
+                let (v, nightly) = match v.find('-') {
+                    Some(i) if &v[i..] == "-nightly" => (&v[..i], true),
+                    _ => (&*v, false),
+                };
+                target = Some(Target { version: RustVersion::parse(v)?, nightly });
+            }
+            "-h" | "--help" => {
+                print!("{USAGE}");
+                return Ok(());
+            }
+            _ if arg.starts_with('-') => return Err(format!("unknown option `{arg}`")),
+            _ if path.is_none() => path = Some(PathBuf::from(arg)),
+            _ => return Err(format!("unexpected argument `{arg}`")),
+        }
+    }
+    let root = path.unwrap_or_else(|| PathBuf::from("."));
+
+    let entries = collect(&root, target.as_ref())?;
+    match format {
+        Format::Table => print!("{}", table(&entries, target.as_ref())),
+        Format::Json => println!("{}", json(&entries)),
+    }
+    Ok(())
+}
+
+fn collect(root: &Path, target: Option<&Target>) -> Result<Vec<Entry>, String> {
+    let mut entries = vec![];
+    for package in scan::packages(root)? {
+        for file in &package.files {
+            for f in scan::const_fns(scan::parse_file(file)?) {
+                let condition =
+                    const_fn_core::proc_macro2::parse_condition(f.args.clone(), &package.dir);
+                let redundant = match condition {
+                    // `#[cfg_attr(<pred>, const_fn)]` is not the same as
+                    // `const fn` even if the condition always holds.
+                    _ if f.cfg_attr.is_some() => Some(false),
+                    Ok(Condition::Always) => Some(true),
+                    Ok(Condition::Version { major, minor }) => {
+                        package.rust_version.map(|msrv| RustVersion { major, minor } <= msrv)
+                    }
+                    Ok(_) => Some(false),
+                    Err(_) => None,
+                };
+                let const_on_target = target.and_then(|target| match condition {
+                    _ if f.cfg_attr.is_some() => None,
+                    Ok(Condition::Always) => Some(true),
+                    Ok(Condition::Version { major, minor }) => {
+                        Some(RustVersion { major, minor } <= target.version)
+                    }
+                    Ok(Condition::Nightly) => Some(target.nightly),
+                    _ => None,
+                });
+                entries.push(Entry {
+                    package: package.name.clone(),
+                    file: file.strip_prefix(root).unwrap_or(file).display().to_string(),
+                    line: f.line,
+                    name: f.name,
+                    vis: f.vis,
+                    args: f.args_text,
+                    cfg_attr: f.cfg_attr,
+                    condition,
+                    redundant,
+                    const_on_target,
+                });
+            }
+        }
+    }
+    Ok(entries)
+}
+
+fn kind(condition: &Result<Condition, String>) -> &'static str {
+    match condition {
+        Ok(Condition::Always) => "always",
+        Ok(Condition::Version { .. }) => "version",
+        Ok(Condition::Nightly) => "nightly",
+        Ok(Condition::Feature(_)) => "feature",
+        Ok(Condition::Cfg(_)) => "cfg",
+        Err(_) => "error",
+    }
+}
+
+fn yes_no(b: Option<bool>) -> &'static str {
+    match b {
+        Some(true) => "yes",
+        Some(false) => "no",
+        None => "-",
+    }
+}
+
+fn table(entries: &[Entry], target: Option<&Target>) -> String {
+    let mut rows = vec![vec![
+        "LOCATION".to_owned(),
+        "FUNCTION".to_owned(),
+        "VISIBILITY".to_owned(),
+        "CONDITION".to_owned(),
+        "REDUNDANT".to_owned(),
+    ]];
+    if let Some(target) = target {
+        rows[0].push(format!(
+            "CONST ON {}{}",
+            target.version,
+            if target.nightly { "-NIGHTLY" } else { "" }
+        ));
+    }
+    for e in entries {
+        let mut row = vec![
+            format!("{}:{}", e.file, e.line),
+            e.name.clone(),
+            if e.vis.is_empty() { "private".to_owned() } else { e.vis.clone() },
+            condition_text(e),
+            yes_no(e.redundant).to_owned(),
+        ];
+        if target.is_some() {
+            row.push(yes_no(e.const_on_target).to_owned());
+        }
+        rows.push(row);
+    }
+
+    let mut out = report::columns(&rows);
+    let redundant = entries.iter().filter(|e| e.redundant == Some(true)).count();
+    let _ = writeln!(out, "\n{}, {redundant} redundant", report::count(entries.len(), "function"));
+    for e in entries {
+        if let Err(msg) = &e.condition {
+            let _ = writeln!(out, "error: {}:{}: {}", e.file, e.line, msg);
+        }
+    }
+    out
+}
+
+// The arguments as written, with the predicate of `cfg_attr` if any.
+fn condition_text(e: &Entry) -> String {
+    let args = if e.args.is_empty() { "(none)" } else { &e.args };
+    match &e.cfg_attr {
+        Some(pred) => format!("{args} if {pred}"),
+        None => args.to_owned(),
+    }
+}
+
+fn json(entries: &[Entry]) -> String {
+    let entries: Vec<Value> = entries
+        .iter()
+        .map(|e| {
+            let mut v = json!({
+                "package": e.package,
+                "file": e.file,
+                "line": e.line,
+                "name": e.name,
+                "visibility": e.vis,
+                "condition": e.args,
+                "kind": kind(&e.condition),
+                "redundant": e.redundant,
+                "const_on_target": e.const_on_target,
+            });
+            match &e.condition {
+                Ok(Condition::Version { major, minor }) => {
+                    v["version"] = json!(format!("{major}.{minor}"));
+                }
+                Ok(Condition::Feature(name)) => v["feature"] = json!(name),
+                Ok(Condition::Cfg(pred)) => v["cfg"] = json!(pred),
+                Err(msg) => v["error"] = json!(msg),
+                Ok(Condition::Always | Condition::Nightly) => {}
+            }
+            if let Some(pred) = &e.cfg_attr {
+                v["cfg_attr"] = json!(pred);
+            }
+            v
+        })
+        .collect();
+    serde_json::to_string_pretty(&entries).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(condition: Result<Condition, String>, redundant: Option<bool>) -> Entry {
+        Entry {
+            package: "a".to_owned(),
+            file: "src/lib.rs".to_owned(),
+            line: 1,
+            name: "f".to_owned(),
+            vis: "pub".to_owned(),
+            args: "\"1.61\"".to_owned(),
+            cfg_attr: None,
+            condition,
+            redundant,
+            const_on_target: None,
+        }
+    }
+
+    #[test]
+    fn json_output() {
+        let parse = |s: String| serde_json::from_str::<Value>(&s).unwrap();
+        assert_eq!(json(&[]), "[]");
+        assert_eq!(
+            parse(json(&[entry(Ok(Condition::Version { major: 1, minor: 61 }), Some(true))])),
+            json!([{
+                "package": "a",
+                "file": "src/lib.rs",
+                "line": 1,
+                "name": "f",
+                "visibility": "pub",
+                "condition": "\"1.61\"",
+                "kind": "version",
+                "version": "1.61",
+                "redundant": true,
+                "const_on_target": null,
+            }])
+        );
+        let v = parse(json(&[entry(Err("a\n\"b\"".to_owned()), None)]));
+        assert_eq!(v[0]["error"], "a\n\"b\"");
+        assert_eq!(v[0]["redundant"], Value::Null);
+    }
+
+    #[test]
+    fn table_output() {
+        let target = Target { version: RustVersion { major: 1, minor: 70 }, nightly: false };
+        let mut e = entry(Ok(Condition::Version { major: 1, minor: 61 }), Some(false));
+        e.const_on_target = Some(true);
+        assert_eq!(
+            table(&[e], Some(&target)),
+            "LOCATION      FUNCTION  VISIBILITY  CONDITION  REDUNDANT  CONST ON 1.70\n\
+             src/lib.rs:1  f         pub         \"1.61\"     no         yes\n\
+             \n\
+             1 function, 0 redundant\n"
+        );
+
+        let mut e = entry(Ok(Condition::Always), Some(false));
+        e.args = String::new();
+        e.cfg_attr = Some("feature = \"a\"".to_owned());
+        assert_eq!(
+            table(&[e], None),
+            "LOCATION      FUNCTION  VISIBILITY  CONDITION                REDUNDANT\n\
+             src/lib.rs:1  f         pub         (none) if feature = \"a\"  no\n\
+             \n\
+             1 function, 0 redundant\n"
+        );
+    }
+}
@@ -0,0 +1,340 @@
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+// `cargo const-fn candidates`: lists non-const functions that could be
+// `const fn`, and the `#[const_fn]` argument for them.
+
+use std::{
+    collections::HashMap,
+    fmt::Write as _,
+    path::{Path, PathBuf},
+};
+
+use const_fn_core::Condition;
+use serde_json::{Value, json};
+
+use crate::{
+    constructs::{self, Analysis, Requirement},
+    metadata::RustVersion,
+    report::{self, Format},
+    scan::{self, Context, Function},
+};
+
+const USAGE: &str = "\
+Lists non-const functions that could be `const fn`, and the #[const_fn] argument
+for them
+
+USAGE:
+    cargo const-fn candidates [OPTIONS] [PATH]
+
+ARGS:
+    <PATH>    Directory of the package or workspace to scan [default: .]
+
+OPTIONS:
+        --format <FORMAT>          Output format: table or json [default: table]
+        --rustc-version <VERSION>  Only list functions that can be `const fn` on the
+                                   given compiler version (e.g., 1.61)
+    -h, --help                     Print help information
+";
+
+// Attributes of functions that cannot or should not be `const fn`.
+const SKIPPED_ATTRS: &[&str] =
+    &["bench", "proc_macro", "proc_macro_attribute", "proc_macro_derive", "test"];
+
+struct Entry {
+    package: String,
+    file: String,
+    line: usize,
+    name: String,
+    version: RustVersion,
+    // The construct that requires the version.
+    reason: String,
+    // `const fn` or `#[const_fn("1.XX")]`.
+    suggestion: String,
+}
+
+pub(crate) fn run(args: impl Iterator<Item = String>) -> Result<(), String> {
+    let mut path = None;
+    let mut format = Format::Table;
+    let mut target = None;
+    let mut args = args;
+    while let Some(arg) = args.next() {
+        match &*arg {
+            "--format" => format = Format::parse(&crate::value(&mut args, &arg)?)?,
+            "--rustc-version" => {
+                target = Some(RustVersion::parse(&crate::value(&mut args, &arg)?)?);
+            }
+            "-h" | "--help" => {
+                print!("{USAGE}");
+                return Ok(());
+            }
+            _ if arg.starts_with('-') => return Err(format!("unknown option `{arg}`")),
+            _ if path.is_none() => path = Some(PathBuf::from(arg)),
+            _ => return Err(format!("unexpected argument `{arg}`")),
+        }
+    }
+    let root = path.unwrap_or_else(|| PathBuf::from("."));
+
+    let mut entries = vec![];
+    for package in scan::packages(&root)? {
+        let mut fns = vec![];
+        for file in &package.files {
+            let file_name = file.strip_prefix(&root).unwrap_or(file).display().to_string();
+            for f in scan::functions(scan::parse_file(file)?) {
+                fns.push((file_name.clone(), f));
+            }
+        }
+        let msrv = package.rust_version.unwrap_or(constructs::MIN);
+        for (file, f, required) in candidates(fns, &package.dir) {
+            let version = match required.version {
+                Some(v) if target.map_or(true, |target| v <= target) => v,
+                _ => continue,
+            };
+            entries.push(Entry {
+                package: package.name.clone(),
+                file,
+                line: f.line,
+                name: f.name,
+                version,
+                reason: required.reason,
+                suggestion: if version <= msrv {
+                    "const fn".to_owned()
+                } else {
+                    format!("#[const_fn(\"{version}\")]")
+                },
+            });
+        }
+    }
+    match format {
+        Format::Table => print!("{}", table(&entries)),
+        Format::Json => println!("{}", json(&entries)),
+    }
+    Ok(())
+}
+
+// Returns the non-const functions in a package with their requirements.
+fn candidates(fns: Vec<(String, Function)>, dir: &Path) -> Vec<(String, Function, Requirement)> {
+    // The requirements of the functions that cannot be candidates, by name.
+    let mut known: HashMap<String, Requirement> = HashMap::new();
+    let mut candidates: Vec<(String, Function, Analysis)> = vec![];
+    for (file, f) in fns {
+        let required = if f.qualifiers.iter().any(|q| q == "const") {
+            Requirement::new(Some(constructs::MIN), "")
+        } else if let Some(attr) = &f.const_fn {
+            match const_fn_core::proc_macro2::parse_condition(attr.args.clone(), dir) {
+                // `#[cfg_attr(<pred>, const_fn)]` depends on cfgs.
+                _ if attr.cfg_attr.is_some() => Requirement::new(None, ""),
+                Ok(Condition::Always) => Requirement::new(Some(constructs::MIN), ""),
+                Ok(Condition::Version { major, minor }) => {
+                    Requirement::new(Some(RustVersion { major, minor }), "")
+                }
+                _ => Requirement::new(None, ""),
+            }
+        } else if is_candidate(&f) {
+            let analysis = constructs::analyze(&f);
+            candidates.push((file, f, analysis));
+            continue;
+        } else {
+            Requirement::new(None, "")
+        };
+        insert(&mut known, &f.name, required);
+    }
+
+    // Propagate the requirements of the called functions until they no longer
+    // change. Requirements only increase, so this terminates.
+    let mut required: Vec<_> = candidates.iter().map(|(_, _, a)| a.required.clone()).collect();
+    loop {
+        let mut by_name = known.clone();
+        for ((_, f, _), r) in candidates.iter().zip(&required) {
+            insert(&mut by_name, &f.name, r.clone());
+        }
+        let mut changed = false;
+        for ((_, f, a), r) in candidates.iter().zip(&mut required) {
+            let mut new = a.required.clone();
+            for name in &a.calls {
+                // A call with the same name is more likely a call to a method
+                // of a field (e.g., `self.0.len()` in `len`) than recursion.
+                let version =
+                    if *name == f.name { None } else { by_name.get(name).and_then(|r| r.version) };
+                new = new.max(Requirement::new(version, format!("calls `{name}`")));
+            }
+            if new.version != r.version {
+                *r = new;
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+    candidates.into_iter().zip(required).map(|((file, f, _), r)| (file, f, r)).collect()
+}
+
+fn is_candidate(f: &Function) -> bool {
+    matches!(f.context, Context::Module | Context::Impl)
+        && f.name != "main"
+        && !f.attrs.iter().any(|attr| {
+            let name = attr.rsplit("::").next().unwrap_or(attr);
+            SKIPPED_ATTRS.contains(&name)
+        })
+}
+
+// Functions with the same name are not distinguished, so keep the highest
+// requirement.
+fn insert(map: &mut HashMap<String, Requirement>, name: &str, required: Requirement) {
+    let required = match map.remove(name) {
+        Some(r) => r.max(required),
+        None => required,
+    };
+    map.insert(name.to_owned(), required);
+}
+
+fn table(entries: &[Entry]) -> String {
+    let mut rows = vec![vec![
+        "LOCATION".to_owned(),
+        "FUNCTION".to_owned(),
+        "REQUIRES".to_owned(),
+        "REASON".to_owned(),
+        "SUGGESTION".to_owned(),
+    ]];
+    for e in entries {
+        rows.push(vec![
+            format!("{}:{}", e.file, e.line),
+            e.name.clone(),
+            e.version.to_string(),
+            e.reason.clone(),
+            e.suggestion.clone(),
+        ]);
+    }
+    let mut out = report::columns(&rows);
+    let _ = writeln!(out, "\n{}", report::count(entries.len(), "candidate"));
+    out
+}
+
+fn json(entries: &[Entry]) -> String {
+    let entries: Vec<Value> = entries
+        .iter()
+        .map(|e| {
+            json!({
+                "package": e.package,
+                "file": e.file,
+                "line": e.line,
+                "name": e.name,
+                "version": e.version.to_string(),
+                "reason": e.reason,
+                "suggestion": e.suggestion,
+            })
+        })
+        .collect();
+    serde_json::to_string_pretty(&entries).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_candidates() {
+        let src = r#"
+const fn a() -> u8 { 1 }
+
+#[const_fn("1.61")]
+fn b() -> u8 { 1 }
+
+#[const_fn(nightly)]
+fn c() -> u8 { 1 }
+
+fn d() -> u8 { a() + b() }
+fn e() -> u8 { c() }
+fn f() -> u8 { g() }
+fn g() -> u8 { if h() { f() } else { 0 } }
+fn h() -> bool { true }
+fn i() -> String { String::from("") }
+
+impl S {
+    fn len(&self) -> usize { self.0.len() }
+    fn j(&self) -> u8 { Self::k(1) }
+    fn k(x: u32) -> u8 { x.count_ones() as u8 }
+    fn n(&self) -> u8 { self.0.n() }
+}
+
+fn o(s: &String) -> bool { s.is_empty() }
+fn p(s: &str) -> bool { s.is_empty() }
+fn q(v: Vec<u8>) -> usize { v.len() }
+
+impl Clone for S {
+    fn clone(&self) -> Self { *self }
+}
+fn l(s: &S) -> S { s.clone() }
+
+#[test]
+fn m() {}
+fn main() {}
+"#;
+        let fns =
+            scan::functions(src.parse().unwrap()).into_iter().map(|f| (String::new(), f)).collect();
+        let candidates: Vec<_> = candidates(fns, Path::new("."))
+            .into_iter()
+            .map(|(_, f, r)| {
+                format!(
+                    "{} {} {}",
+                    f.name,
+                    r.version.map_or("-".to_owned(), |v| v.to_string()),
+                    r.reason
+                )
+            })
+            .collect();
+        assert_eq!(
+            candidates,
+            [
+                "d 1.61 calls `b`",
+                "e - calls `c`",
+                "f 1.46 calls `g`",
+                "g 1.46 `if`",
+                "h 1.31 `const fn`",
+                "i - calls `from`",
+                "len - calls `len`",
+                "j 1.32 calls `k`",
+                "k 1.32 `.count_ones()`",
+                "n - calls `n`",
+                "o - calls `is_empty`",
+                "p 1.39 `.is_empty()`",
+                "q - by-value parameters that may have destructors",
+                "l - calls `clone`",
+            ]
+        );
+    }
+
+    #[test]
+    fn output() {
+        let entry = Entry {
+            package: "a".to_owned(),
+            file: "src/lib.rs".to_owned(),
+            line: 1,
+            name: "f".to_owned(),
+            version: RustVersion { major: 1, minor: 46 },
+            reason: "`if`".to_owned(),
+            suggestion: "#[const_fn(\"1.46\")]".to_owned(),
+        };
+        assert_eq!(
+            table(std::slice::from_ref(&entry)),
+            "LOCATION      FUNCTION  REQUIRES  REASON  SUGGESTION\n\
+             src/lib.rs:1  f         1.46      `if`    #[const_fn(\"1.46\")]\n\
+             \n\
+             1 candidate\n"
+        );
+        assert_eq!(json(&[]), "[]");
+        let entry = Entry { reason: "calls `f` with \"a\\b\"".to_owned(), ..entry };
+        assert_eq!(
+            serde_json::from_str::<Value>(&json(&[entry])).unwrap(),
+            json!([{
+                "package": "a",
+                "file": "src/lib.rs",
+                "line": 1,
+                "name": "f",
+                "version": "1.46",
+                "reason": "calls `f` with \"a\\b\"",
+                "suggestion": "#[const_fn(\"1.46\")]",
+            }])
+        );
+    }
+}
@@ -0,0 +1,902 @@
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+// Tables of the constructs allowed in `const fn` and the Rust versions that
+// allowed them, and a check of function signatures and bodies against them.
+//
+// This works on tokens, without name resolution, so it is a heuristic. Calls
+// and macros that are not in the tables are treated as non-const (unless the
+// caller resolves them to functions in the package), and the types of
+// parameters and local variables are only known if they are written as types
+// the tables know, such as integers, slices, or `Option`. Methods are only
+// matched if the type of the receiver is known, and by-value parameters and
+// local variables whose types may have destructors make the function
+// non-const. Suggestions can still be wrong, e.g., for items that shadow the
+// ones of the standard library.
+
+use std::collections::HashMap;
+
+use proc_macro2::{Delimiter, Literal, Spacing, TokenTree};
+
+use crate::{metadata::RustVersion, scan::Function};
+
+const fn v(minor: u32) -> RustVersion {
+    RustVersion { major: 1, minor }
+}
+
+// The version that stabilized `const fn`.
+pub(crate) const MIN: RustVersion = v(31);
+
+// Keywords, and the versions that allowed them, or `None` if they are not
+// allowed in `const fn` on stable.
+const KEYWORDS: &[(&str, Option<RustVersion>, &str)] = &[
+    ("let", Some(v(33)), "`let` bindings"),
+    ("if", Some(v(46)), "`if`"),
+    ("match", Some(v(46)), "`match`"),
+    ("while", Some(v(46)), "`while`"),
+    ("loop", Some(v(46)), "`loop`"),
+    // Dereferencing raw pointers requires 1.58.
+    ("unsafe", Some(v(58)), "`unsafe` blocks"),
+    ("impl", Some(v(61)), "`impl Trait`"),
+    ("dyn", Some(v(61)), "`dyn Trait`"),
+    ("f32", Some(v(82)), "floating point arithmetic"),
+    ("f64", Some(v(82)), "floating point arithmetic"),
+    ("for", None, "`for` loops"),
+    ("async", None, "`async` blocks"),
+    ("await", None, "`.await`"),
+    ("move", None, "closures"),
+];
+
+// Macros that expand to const-compatible code.
+const MACROS: &[(&str, RustVersion)] = &[
+    ("cfg", MIN),
+    ("column", MIN),
+    ("concat", MIN),
+    ("env", MIN),
+    ("file", MIN),
+    ("include_bytes", MIN),
+    ("include_str", MIN),
+    ("line", MIN),
+    ("module_path", MIN),
+    ("option_env", MIN),
+    ("stringify", MIN),
+    ("matches", v(46)),
+    // Only with a string literal message; see `mac`.
+    ("assert", v(57)),
+    ("debug_assert", v(57)),
+    ("panic", v(57)),
+    ("todo", v(57)),
+    ("unimplemented", v(57)),
+    ("unreachable", v(57)),
+];
+
+// Types of receivers whose methods are in `METHODS`.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Receiver {
+    // integers other than `u8`
+    Int,
+    U8,
+    Char,
+    Str,
+    // slices and arrays
+    Slice,
+    Option,
+    Result,
+}
+
+const INTS: &[Receiver] = &[Receiver::Int, Receiver::U8];
+const ASCII: &[Receiver] = &[Receiver::U8, Receiver::Char];
+const STR: &[Receiver] = &[Receiver::Str];
+const SLICE: &[Receiver] = &[Receiver::Slice];
+const SLICE_STR: &[Receiver] = &[Receiver::Slice, Receiver::Str];
+const OPTION_RESULT: &[Receiver] = &[Receiver::Option, Receiver::Result];
+
+// Const methods of the standard library, matched by name and the type of the
+// receiver.
+const METHODS: &[(&str, &[Receiver], RustVersion)] = &[
+    // integers
+    ("abs", INTS, v(32)),
+    ("count_ones", INTS, v(32)),
+    ("count_zeros", INTS, v(32)),
+    ("is_negative", INTS, v(32)),
+    ("is_positive", INTS, v(32)),
+    ("is_power_of_two", INTS, v(32)),
+    ("leading_zeros", INTS, v(32)),
+    ("overflowing_add", INTS, v(32)),
+    ("overflowing_mul", INTS, v(32)),
+    ("overflowing_sub", INTS, v(32)),
+    ("rotate_left", INTS, v(32)),
+    ("rotate_right", INTS, v(32)),
+    ("swap_bytes", INTS, v(32)),
+    ("to_be", INTS, v(32)),
+    ("to_le", INTS, v(32)),
+    ("trailing_zeros", INTS, v(32)),
+    ("wrapping_add", INTS, v(32)),
+    ("wrapping_mul", INTS, v(32)),
+    ("wrapping_neg", INTS, v(32)),
+    ("wrapping_shl", INTS, v(32)),
+    ("wrapping_shr", INTS, v(32)),
+    ("wrapping_sub", INTS, v(32)),
+    ("reverse_bits", INTS, v(37)),
+    ("to_be_bytes", INTS, v(44)),
+    ("to_le_bytes", INTS, v(44)),
+    ("to_ne_bytes", INTS, v(44)),
+    ("leading_ones", INTS, v(46)),
+    ("trailing_ones", INTS, v(46)),
+    ("checked_add", INTS, v(47)),
+    ("checked_mul", INTS, v(47)),
+    ("checked_sub", INTS, v(47)),
+    ("saturating_add", INTS, v(47)),
+    ("saturating_mul", INTS, v(47)),
+    ("saturating_sub", INTS, v(47)),
+    ("signum", INTS, v(47)),
+    ("checked_pow", INTS, v(50)),
+    ("pow", INTS, v(50)),
+    ("saturating_pow", INTS, v(50)),
+    ("wrapping_pow", INTS, v(50)),
+    // `u8` and `char`
+    ("is_ascii_alphabetic", ASCII, v(47)),
+    ("is_ascii_alphanumeric", ASCII, v(47)),
+    ("is_ascii_digit", ASCII, v(47)),
+    ("is_ascii_lowercase", ASCII, v(47)),
+    ("is_ascii_uppercase", ASCII, v(47)),
+    ("is_ascii_whitespace", ASCII, v(47)),
+    ("to_ascii_lowercase", ASCII, v(52)),
+    ("to_ascii_uppercase", ASCII, v(52)),
+    // slices and `str`
+    ("as_ptr", SLICE_STR, v(32)),
+    ("as_bytes", STR, v(39)),
+    ("is_empty", SLICE_STR, v(39)),
+    ("len", SLICE_STR, v(39)),
+    ("first", SLICE, v(56)),
+    ("last", SLICE, v(56)),
+    // `Option` and `Result`
+    ("as_ref", OPTION_RESULT, v(48)),
+    ("is_err", OPTION_RESULT, v(48)),
+    ("is_none", OPTION_RESULT, v(48)),
+    ("is_ok", OPTION_RESULT, v(48)),
+    ("is_some", OPTION_RESULT, v(48)),
+];
+
+// Const functions of the standard library, matched by the last segment of the
+// path, or the last two segments if the name is ambiguous.
+const FUNCTIONS: &[(&str, RustVersion)] = &[
+    ("align_of", MIN),
+    ("null", MIN),
+    ("null_mut", MIN),
+    ("size_of", MIN),
+    ("Cell::new", MIN),
+    ("RefCell::new", MIN),
+    ("UnsafeCell::new", v(32)),
+    ("Duration::from_micros", v(32)),
+    ("Duration::from_millis", v(32)),
+    ("Duration::from_nanos", v(32)),
+    ("Duration::from_secs", v(32)),
+    ("from_be", v(32)),
+    ("from_le", v(32)),
+    ("max_value", v(32)),
+    ("min_value", v(32)),
+    ("String::new", v(39)),
+    ("Vec::new", v(39)),
+    ("from_be_bytes", v(44)),
+    ("from_le_bytes", v(44)),
+    ("from_ne_bytes", v(44)),
+    ("forget", v(46)),
+    ("transmute", v(56)),
+    ("Duration::new", v(58)),
+    ("mem::replace", v(83)),
+];
+
+// Identifiers followed by parentheses that are not calls.
+const NOT_CALLS: &[&str] = &[
+    "as", "break", "else", "for", "if", "in", "let", "match", "move", "mut", "ref", "return",
+    "while",
+];
+
+const INT_SUFFIXES: &[&str] =
+    &["u8", "u16", "u32", "u64", "u128", "usize", "i8", "i16", "i32", "i64", "i128", "isize"];
+
+// The Rust version required for a function to be `const fn`.
+#[derive(Clone)]
+pub(crate) struct Requirement {
+    // `None` if the function cannot be `const fn` on stable.
+    pub(crate) version: Option<RustVersion>,
+    // The construct that requires the version.
+    pub(crate) reason: String,
+}
+
+impl Requirement {
+    pub(crate) fn new(version: Option<RustVersion>, reason: impl Into<String>) -> Self {
+        Self { version, reason: reason.into() }
+    }
+
+    // Returns the higher requirement. `None` is higher than any version.
+    pub(crate) fn max(self, other: Self) -> Self {
+        match (self.version, other.version) {
+            (None, _) => self,
+            (_, None) => other,
+            (Some(a), Some(b)) => {
+                if b > a {
+                    other
+                } else {
+                    self
+                }
+            }
+        }
+    }
+}
+
+pub(crate) struct Analysis {
+    pub(crate) required: Requirement,
+    // Functions and methods that are not in the tables. The caller resolves
+    // them to the functions in the package.
+    pub(crate) calls: Vec<String>,
+    // The types of the parameters and local variables, by name.
+    bindings: HashMap<String, Type>,
+    // Type parameters bounded by `Copy`.
+    copy_params: Vec<String>,
+}
+
+impl Analysis {
+    fn require(&mut self, version: Option<RustVersion>, reason: &str) {
+        let required = Requirement::new(version, reason);
+        self.required = self.required.clone().max(required);
+    }
+}
+
+// Returns the Rust version required for `f` to be `const fn`.
+pub(crate) fn analyze(f: &Function) -> Analysis {
+    let mut a = Analysis {
+        required: Requirement::new(Some(MIN), "`const fn`"),
+        calls: vec![],
+        bindings: HashMap::new(),
+        copy_params: copy_params(&f.sig),
+    };
+    let mut qualifiers = f.qualifiers.iter();
+    while let Some(q) = qualifiers.next() {
+        match &**q {
+            "async" => a.require(None, "`async fn`"),
+            "unsafe" => a.require(Some(v(33)), "`const unsafe fn`"),
+            "extern" => match qualifiers.next().map(String::as_str) {
+                None | Some("\"C\"" | "\"Rust\"") => a.require(Some(v(62)), "`extern \"C\" fn`"),
+                Some(_) => a.require(Some(v(83)), "`extern` functions with other ABIs"),
+            },
+            _ => {}
+        }
+    }
+    signature(&f.sig, true, &mut a);
+    params(&f.sig, &mut a);
+    body(&f.body.clone().into_iter().collect::<Vec<_>>(), &mut a);
+    a
+}
+
+fn signature(tokens: &[TokenTree], top: bool, a: &mut Analysis) {
+    for (i, tt) in tokens.iter().enumerate() {
+        match tt {
+            TokenTree::Ident(ident) if ident == "fn" => a.require(Some(v(61)), "function pointers"),
+            TokenTree::Ident(ident) => keyword(&ident.to_string(), a),
+            TokenTree::Punct(p) if p.as_char() == '&' => {
+                if matches!(tokens.get(i + 1), Some(TokenTree::Ident(i)) if i == "mut") {
+                    a.require(Some(v(83)), "mutable references");
+                }
+            }
+            // `T: Trait` in generics or where clauses, except lifetime bounds,
+            // `?Sized`, and const generics.
+            TokenTree::Punct(p) if top && p.as_char() == ':' && p.spacing() == Spacing::Alone => {
+                let path_sep = i > 0 && is_punct(&tokens[i - 1], ':');
+                let const_param =
+                    i > 1 && matches!(&tokens[i - 2], TokenTree::Ident(i) if i == "const");
+                let lifetime_or_maybe =
+                    tokens.get(i + 1).is_some_and(|tt| is_punct(tt, '\'') || is_punct(tt, '?'));
+                if !path_sep && !const_param && !lifetime_or_maybe {
+                    a.require(Some(v(61)), "trait bounds");
+                }
+            }
+            TokenTree::Group(g) => {
+                signature(&g.stream().into_iter().collect::<Vec<_>>(), false, a);
+            }
+            _ => {}
+        }
+    }
+}
+
+fn body(tokens: &[TokenTree], a: &mut Analysis) {
+    for (i, tt) in tokens.iter().enumerate() {
+        match tt {
+            TokenTree::Ident(ident) => {
+                let name = ident.to_string();
+                match (tokens.get(i + 1), tokens.get(i + 2)) {
+                    (Some(TokenTree::Punct(p)), Some(TokenTree::Group(g)))
+                        if p.as_char() == '!' =>
+                    {
+                        mac(&name, &g.stream().into_iter().collect::<Vec<_>>(), a);
+                    }
+                    _ => {
+                        let if_or_while_let = i > 0
+                            && matches!(&tokens[i - 1], TokenTree::Ident(i) if i == "if" || i == "while");
+                        if name == "let" && !if_or_while_let {
+                            local(&tokens[i + 1..], a);
+                        }
+                        keyword(&name, a);
+                    }
+                }
+            }
+            TokenTree::Punct(p) => punct(tokens, i, p.as_char(), a),
+            TokenTree::Literal(lit) => {
+                // `x.0.1` is lexed as `x` `.` `0.1`.
+                if is_float(&lit.to_string()) && !(i > 0 && is_punct(&tokens[i - 1], '.')) {
+                    a.require(Some(v(82)), "floating point arithmetic");
+                }
+            }
+            TokenTree::Group(g) => {
+                let stream: Vec<_> = g.stream().into_iter().collect();
+                match g.delimiter() {
+                    Delimiter::Parenthesis => match callee(tokens, i) {
+                        Some(Callee::Function(path)) => function(&path, a),
+                        Some(Callee::Method(name, r)) => {
+                            let receiver = receiver(tokens, r, a);
+                            method(&name, receiver, a);
+                        }
+                        None => {}
+                    },
+                    Delimiter::Bracket => {
+                        let indexing = i > 0
+                            && match &tokens[i - 1] {
+                                TokenTree::Ident(i) => !NOT_CALLS.contains(&&*i.to_string()),
+                                TokenTree::Group(g) => g.delimiter() != Delimiter::Brace,
+                                _ => false,
+                            };
+                        let range =
+                            stream.windows(2).any(|w| is_punct(&w[0], '.') && is_punct(&w[1], '.'));
+                        if indexing && range {
+                            a.require(None, "range indexing");
+                        }
+                    }
+                    _ => {}
+                }
+                body(&stream, a);
+            }
+        }
+    }
+}
+
+fn keyword(name: &str, a: &mut Analysis) {
+    if let Some(&(_, version, reason)) = KEYWORDS.iter().find(|(k, ..)| *k == name) {
+        a.require(version, reason);
+    }
+}
+
+fn punct(tokens: &[TokenTree], i: usize, c: char, a: &mut Analysis) {
+    let joint = |i: usize, c: char| matches!(&tokens[i], TokenTree::Punct(p) if p.as_char() == c && p.spacing() == Spacing::Joint);
+    let next = tokens.get(i + 1);
+    match c {
+        '?' => a.require(None, "`?` operator"),
+        '&' if matches!(next, Some(TokenTree::Ident(i)) if i == "mut") => {
+            a.require(Some(v(83)), "mutable references");
+        }
+        '&' | '|' if joint(i, c) && next.is_some_and(|tt| is_punct(tt, c)) => {
+            a.require(Some(v(46)), "`&&` and `||`");
+        }
+        '=' if !joint(i, '=') => {
+            let prev = if i > 0 { &tokens[i - 1] } else { return };
+            match prev {
+                // `==`, `!=`, `<=`, `>=`, `..=`
+                TokenTree::Punct(p)
+                    if p.spacing() == Spacing::Joint && "=!<>.".contains(p.as_char()) => {}
+                _ => a.require(Some(v(33)), "assignments"),
+            }
+        }
+        _ => {}
+    }
+}
+
+fn mac(name: &str, args: &[TokenTree], a: &mut Analysis) {
+    let version = MACROS.iter().find(|(m, _)| *m == name).map(|&(_, v)| v);
+    let commas = args.iter().filter(|tt| is_punct(tt, ',')).count();
+    let formatted = match name {
+        "assert" | "debug_assert" => commas > 1,
+        "panic" | "todo" | "unimplemented" | "unreachable" => commas > 0,
+        _ => false,
+    };
+    if formatted {
+        a.require(None, &format!("`{name}!` with formatting arguments"));
+    } else {
+        a.require(version, &format!("`{name}!`"));
+    }
+}
+
+enum Callee {
+    // The path of a function.
+    Function(Vec<String>),
+    // The name of a method, and the index of the last token of the receiver.
+    Method(String, usize),
+}
+
+// Returns the callee if the group at `i` is the arguments of a call.
+fn callee(tokens: &[TokenTree], i: usize) -> Option<Callee> {
+    let mut k = i.checked_sub(1)?;
+    // `f::<T>()`
+    if is_punct(&tokens[k], '>') {
+        let mut depth = 0;
+        loop {
+            if is_punct(&tokens[k], '>') {
+                depth += 1;
+            } else if is_punct(&tokens[k], '<') {
+                depth -= 1;
+                if depth == 0 {
+                    break;
+                }
+            }
+            k = k.checked_sub(1)?;
+        }
+        k = k.checked_sub(3)?;
+        if !is_punct(&tokens[k + 1], ':') || !is_punct(&tokens[k + 2], ':') {
+            return None;
+        }
+    }
+    let name = match &tokens[k] {
+        TokenTree::Ident(i) => i.to_string(),
+        _ => return None,
+    };
+    if NOT_CALLS.contains(&&*name) {
+        return None;
+    }
+    if k > 0 && is_punct(&tokens[k - 1], '.') {
+        return Some(Callee::Method(name, k.checked_sub(2)?));
+    }
+    let mut path = vec![name];
+    if k > 2 && is_punct(&tokens[k - 1], ':') && is_punct(&tokens[k - 2], ':') {
+        if let TokenTree::Ident(i) = &tokens[k - 3] {
+            path.insert(0, i.to_string());
+        }
+    }
+    Some(Callee::Function(path))
+}
+
+fn function(path: &[String], a: &mut Analysis) {
+    let name = &path[path.len() - 1];
+    // tuple struct and tuple variant constructors
+    if name.starts_with(|c: char| c.is_ascii_uppercase()) {
+        return;
+    }
+    let full = path.join("::");
+    match FUNCTIONS.iter().find(|(f, _)| *f == full || f == name) {
+        Some(&(_, version)) => a.require(Some(version), &format!("`{full}()`")),
+        None => a.calls.push(name.clone()),
+    }
+}
+
+// Methods are only matched if the type of the receiver is known; other
+// methods are resolved by the caller like functions.
+fn method(name: &str, receiver: Option<Receiver>, a: &mut Analysis) {
+    let method = receiver
+        .and_then(|r| METHODS.iter().find(|(m, types, _)| *m == name && types.contains(&r)));
+    match method {
+        Some(&(_, _, version)) => a.require(Some(version), &format!("`.{name}()`")),
+        None => a.calls.push(name.to_owned()),
+    }
+}
+
+// Returns the type of the receiver ending at `r`, if it is a literal or a
+// variable of a known type.
+fn receiver(tokens: &[TokenTree], r: usize, a: &Analysis) -> Option<Receiver> {
+    // fields and paths, e.g., `self.0` or `S::X`
+    if r > 0 && (is_punct(&tokens[r - 1], '.') || is_punct(&tokens[r - 1], ':')) {
+        return None;
+    }
+    match &tokens[r] {
+        TokenTree::Literal(lit) => literal_type(lit).receiver,
+        TokenTree::Ident(i) => a.bindings.get(&i.to_string()).and_then(|ty| ty.receiver),
+        _ => None,
+    }
+}
+
+// What is known about the type of a parameter or a local variable.
+#[derive(Clone, Copy)]
+struct Type {
+    // The type after dereferencing, if it has methods in `METHODS`.
+    receiver: Option<Receiver>,
+    // Whether the type is known to have no destructor, e.g., primitive types,
+    // references, and type parameters bounded by `Copy`. Dropping a value of
+    // other types may not be allowed in `const fn` (E0493).
+    no_drop: bool,
+}
+
+const UNKNOWN: Type = Type { receiver: None, no_drop: false };
+
+// Returns the type parameters bounded by `Copy` in the generics or the where
+// clause.
+fn copy_params(sig: &[TokenTree]) -> Vec<String> {
+    let mut bounds = vec![];
+    if sig.first().is_some_and(|tt| is_punct(tt, '<')) {
+        if let Some(end) = closing_angle(sig) {
+            bounds.extend(split_commas(&sig[1..end]));
+        }
+    }
+    if let Some(i) = sig.iter().position(|tt| matches!(tt, TokenTree::Ident(i) if i == "where")) {
+        bounds.extend(split_commas(&sig[i + 1..]));
+    }
+    let mut params = vec![];
+    for bound in bounds {
+        if let [TokenTree::Ident(name), TokenTree::Punct(p), rest @ ..] = bound {
+            let copy = rest.iter().any(|tt| matches!(tt, TokenTree::Ident(i) if i == "Copy"));
+            // not `T::Assoc: Copy`
+            if p.as_char() == ':' && p.spacing() == Spacing::Alone && copy {
+                params.push(name.to_string());
+            }
+        }
+    }
+    params
+}
+
+// Checks the types of the parameters, and records them.
+fn params(sig: &[TokenTree], a: &mut Analysis) {
+    // The first parenthesized group outside of the generics.
+    let mut depth = 0;
+    let mut group = None;
+    for (i, tt) in sig.iter().enumerate() {
+        match tt {
+            TokenTree::Punct(p) if p.as_char() == '<' => depth += 1,
+            TokenTree::Punct(p) if p.as_char() == '>' && !is_arrow(sig, i) => depth -= 1,
+            TokenTree::Group(g) if depth == 0 && g.delimiter() == Delimiter::Parenthesis => {
+                group = Some(g.stream().into_iter().collect::<Vec<_>>());
+                break;
+            }
+            _ => {}
+        }
+    }
+    let Some(tokens) = group else { return };
+    for mut param in split_commas(&tokens) {
+        // `#[cfg(...)]`
+        while let [TokenTree::Punct(p), TokenTree::Group(_), rest @ ..] = param {
+            if p.as_char() != '#' {
+                break;
+            }
+            param = rest;
+        }
+        let (pat, ty) = match colon(param) {
+            Some(c) => (&param[..c], type_of(&param[c + 1..], a)),
+            // `self`, `mut self`, `&self`, `&'a mut self`
+            None if param.first().is_some_and(|tt| is_punct(tt, '&')) => {
+                (param, Type { receiver: None, no_drop: true })
+            }
+            None => (param, UNKNOWN),
+        };
+        if !ty.no_drop {
+            a.require(None, "by-value parameters that may have destructors");
+        }
+        bind(pat, ty, a);
+    }
+}
+
+// Checks the type of the variable of a `let` statement, and records it.
+fn local(tokens: &[TokenTree], a: &mut Analysis) {
+    let end = tokens.iter().position(|tt| is_punct(tt, ';')).unwrap_or(tokens.len());
+    let tokens = &tokens[..end];
+    // `=`, not `==`, `!=`, `<=`, `>=`, or `=>`
+    let eq = (0..tokens.len()).find(|&i| {
+        is_punct(&tokens[i], '=')
+            && !(i > 0
+                && matches!(&tokens[i - 1], TokenTree::Punct(p) if "=!<>".contains(p.as_char())))
+            && !tokens.get(i + 1).is_some_and(|tt| is_punct(tt, '=') || is_punct(tt, '>'))
+    });
+    let (decl, init) = match eq {
+        Some(eq) => (&tokens[..eq], Some(&tokens[eq + 1..])),
+        None => (tokens, None),
+    };
+    let (pat, ty) = match (colon(decl), init) {
+        (Some(c), _) => (&decl[..c], type_of(&decl[c + 1..], a)),
+        (None, Some(init)) => (decl, expr_type(init, a)),
+        (None, None) => (decl, UNKNOWN),
+    };
+    if !ty.no_drop {
+        a.require(None, "local variables that may have destructors");
+    }
+    bind(pat, ty, a);
+}
+
+// Records the type of a variable if the pattern is `x` or `mut x`.
+fn bind(pat: &[TokenTree], ty: Type, a: &mut Analysis) {
+    let name = match pat {
+        [TokenTree::Ident(name)] => name,
+        [TokenTree::Ident(m), TokenTree::Ident(name)] if m == "mut" => name,
+        _ => return,
+    };
+    a.bindings.insert(name.to_string(), ty);
+}
+
+fn type_of(tokens: &[TokenTree], a: &Analysis) -> Type {
+    let mut tokens = tokens;
+    // `::std::option::Option<T>` -> `Option<T>`
+    loop {
+        tokens = match tokens {
+            [TokenTree::Punct(p1), TokenTree::Punct(p2), rest @ ..]
+            | [TokenTree::Ident(_), TokenTree::Punct(p1), TokenTree::Punct(p2), rest @ ..]
+                if p1.as_char() == ':' && p2.as_char() == ':' =>
+            {
+                rest
+            }
+            _ => break,
+        };
+    }
+    let no_drop = |tokens: &[TokenTree]| type_of(tokens, a).no_drop;
+    match tokens.first() {
+        // references
+        Some(TokenTree::Punct(p)) if p.as_char() == '&' => {
+            let mut rest = &tokens[1..];
+            if rest.first().is_some_and(|tt| is_punct(tt, '\'')) {
+                rest = rest.get(2..).unwrap_or_default();
+            }
+            if matches!(rest.first(), Some(TokenTree::Ident(i)) if i == "mut") {
+                rest = &rest[1..];
+            }
+            Type { receiver: type_of(rest, a).receiver, no_drop: true }
+        }
+        // raw pointers
+        Some(TokenTree::Punct(p)) if p.as_char() == '*' => Type { receiver: None, no_drop: true },
+        Some(TokenTree::Group(g)) => {
+            let inner: Vec<_> = g.stream().into_iter().collect();
+            match g.delimiter() {
+                // `[T]` and `[T; N]`
+                Delimiter::Bracket => {
+                    let elem = inner.split(|tt| is_punct(tt, ';')).next().unwrap_or_default();
+                    Type { receiver: Some(Receiver::Slice), no_drop: no_drop(elem) }
+                }
+                // tuples
+                Delimiter::Parenthesis => {
+                    Type { receiver: None, no_drop: split_commas(&inner).into_iter().all(no_drop) }
+                }
+                _ => UNKNOWN,
+            }
+        }
+        Some(TokenTree::Ident(ident)) => {
+            let name = ident.to_string();
+            // `Option<T>`
+            let args = match tokens.get(1) {
+                Some(tt) if is_punct(tt, '<') => {
+                    closing_angle(tokens).map_or(vec![], |end| split_commas(&tokens[2..end]))
+                }
+                _ => vec![],
+            };
+            let known = |receiver| Type { receiver: Some(receiver), no_drop: true };
+            match &*name {
+                "u8" => known(Receiver::U8),
+                _ if INT_SUFFIXES.contains(&&*name) => known(Receiver::Int),
+                "char" => known(Receiver::Char),
+                "str" => known(Receiver::Str),
+                "Option" | "Result" => Type {
+                    receiver: Some(if name == "Option" {
+                        Receiver::Option
+                    } else {
+                        Receiver::Result
+                    }),
+                    no_drop: !args.is_empty() && args.into_iter().all(no_drop),
+                },
+                "bool" | "f32" | "f64" | "fn" | "unsafe" | "extern" => {
+                    Type { receiver: None, no_drop: true }
+                }
+                "impl" => Type {
+                    receiver: None,
+                    no_drop: tokens
+                        .iter()
+                        .any(|tt| matches!(tt, TokenTree::Ident(i) if i == "Copy")),
+                },
+                _ if tokens.len() == 1 && a.copy_params.contains(&name) => {
+                    Type { receiver: None, no_drop: true }
+                }
+                _ => UNKNOWN,
+            }
+        }
+        _ => UNKNOWN,
+    }
+}
+
+// Identifiers that do not affect whether an expression has a destructor.
+const NO_DROP_IDENTS: &[&str] =
+    &["Err", "None", "Ok", "Some", "as", "else", "false", "if", "match", "true"];
+
+// Returns the type of the initializer of a `let` without a type. If it is not
+// a literal, a variable, or a cast, only whether it has no destructor is
+// inferred, from whether it only consists of literals, operators, variables
+// without destructors, fields, and methods in `METHODS`.
+fn expr_type(tokens: &[TokenTree], a: &Analysis) -> Type {
+    let cast = tokens.iter().rposition(|tt| matches!(tt, TokenTree::Ident(i) if i == "as"));
+    if let Some(i) = cast {
+        return type_of(&tokens[i + 1..], a);
+    }
+    match tokens {
+        [TokenTree::Literal(lit)] => literal_type(lit),
+        [TokenTree::Ident(i)] if a.bindings.contains_key(&i.to_string()) => {
+            a.bindings[&i.to_string()]
+        }
+        _ => Type { receiver: None, no_drop: no_drop(tokens, a) },
+    }
+}
+
+fn no_drop(tokens: &[TokenTree], a: &Analysis) -> bool {
+    tokens.iter().enumerate().all(|(i, tt)| match tt {
+        TokenTree::Literal(_) | TokenTree::Punct(_) => true,
+        TokenTree::Group(g) => no_drop(&g.stream().into_iter().collect::<Vec<_>>(), a),
+        TokenTree::Ident(ident) => {
+            let name = ident.to_string();
+            let prev = if i > 0 { Some(&tokens[i - 1]) } else { None };
+            if prev.is_some_and(|tt| is_punct(tt, '.')) {
+                let is_call = matches!(tokens.get(i + 1), Some(TokenTree::Group(g)) if g.delimiter() == Delimiter::Parenthesis);
+                !is_call || METHODS.iter().any(|(m, ..)| *m == name)
+            } else if matches!(prev, Some(TokenTree::Ident(i)) if i == "as") {
+                true
+            } else {
+                NO_DROP_IDENTS.contains(&&*name) || a.bindings.get(&name).is_some_and(|ty| ty.no_drop)
+            }
+        }
+    })
+}
+
+fn literal_type(lit: &Literal) -> Type {
+    let lit = lit.to_string();
+    let receiver = if lit.starts_with('"') || lit.starts_with("r\"") || lit.starts_with("r#") {
+        Some(Receiver::Str)
+    } else if lit.starts_with("b\"") || lit.starts_with("br") {
+        Some(Receiver::Slice)
+    } else if lit.starts_with("b'") {
+        Some(Receiver::U8)
+    } else if lit.starts_with('\'') {
+        Some(Receiver::Char)
+    } else if lit.starts_with(|c: char| c.is_ascii_digit()) && !is_float(&lit) {
+        Some(if lit.ends_with("u8") { Receiver::U8 } else { Receiver::Int })
+    } else {
+        None
+    };
+    Type { receiver, no_drop: true }
+}
+
+// Returns the position of the `>` that closes the `<` at 1 or 0.
+fn closing_angle(tokens: &[TokenTree]) -> Option<usize> {
+    let mut depth = 0;
+    for (i, tt) in tokens.iter().enumerate() {
+        if is_punct(tt, '<') {
+            depth += 1;
+        } else if is_punct(tt, '>') && !is_arrow(tokens, i) {
+            depth -= 1;
+            if depth == 0 {
+                return Some(i);
+            }
+        }
+    }
+    None
+}
+
+// Splits tokens by commas outside of `<...>`.
+fn split_commas(tokens: &[TokenTree]) -> Vec<&[TokenTree]> {
+    let mut pieces = vec![];
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, tt) in tokens.iter().enumerate() {
+        if is_punct(tt, '<') {
+            depth += 1;
+        } else if is_punct(tt, '>') && !is_arrow(tokens, i) {
+            depth -= 1;
+        } else if is_punct(tt, ',') && depth == 0 {
+            pieces.push(&tokens[start..i]);
+            start = i + 1;
+        }
+    }
+    if start < tokens.len() {
+        pieces.push(&tokens[start..]);
+    }
+    pieces
+}
+
+// Returns the position of the `:` between a pattern and a type.
+fn colon(tokens: &[TokenTree]) -> Option<usize> {
+    (0..tokens.len()).find(|&i| {
+        is_punct(&tokens[i], ':')
+            && !(i > 0 && is_punct(&tokens[i - 1], ':'))
+            && !tokens.get(i + 1).is_some_and(|tt| is_punct(tt, ':'))
+    })
+}
+
+// `->`
+fn is_arrow(tokens: &[TokenTree], i: usize) -> bool {
+    i > 0
+        && matches!(&tokens[i - 1], TokenTree::Punct(p) if p.as_char() == '-' && p.spacing() == Spacing::Joint)
+}
+
+fn is_float(lit: &str) -> bool {
+    lit.starts_with(|c: char| c.is_ascii_digit())
+        && !["0x", "0o", "0b"].iter().any(|p| lit.starts_with(p))
+        && !INT_SUFFIXES.iter().any(|s| lit.ends_with(s))
+        && lit.contains(['.', 'e', 'E', 'f'])
+}
+
+fn is_punct(tt: &TokenTree, c: char) -> bool {
+    matches!(tt, TokenTree::Punct(p) if p.as_char() == c)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scan;
+
+    fn requirement(src: &str) -> (String, String, Vec<String>) {
+        let f = scan::functions(src.parse().unwrap()).pop().unwrap();
+        let a = analyze(&f);
+        let version = a.required.version.map_or("-".to_owned(), |v| v.to_string());
+        (version, a.required.reason, a.calls)
+    }
+
+    #[test]
+    fn requirements() {
+        let ok = |src: &str, version: &str, reason: &str| {
+            let (v, r, calls) = requirement(src);
+            assert_eq!((&*v, &*r), (version, reason), "{src}");
+            assert!(calls.is_empty(), "{}: {:?}", src, calls);
+        };
+        ok("fn f() {}", "1.31", "`const fn`");
+        ok(
+            "fn f<T>(x: &T, y: &T) -> (&T, Option<u8>) { (x, Some(size_of::<T>() as u8)) }",
+            "1.31",
+            "`const fn`",
+        );
+        ok(
+            "fn f<'a, 'b: 'a, T: ?Sized, const N: usize>(x: &'a T) -> &'a T { x }",
+            "1.31",
+            "`const fn`",
+        );
+        ok("fn f(a: u8, b: u8) -> bool { a == b && a <= b }", "1.46", "`&&` and `||`");
+        ok("fn f(x: u8) -> u8 { let y = x; y }", "1.33", "`let` bindings");
+        ok("fn f(mut x: u8) -> u8 { x += 1; x }", "1.33", "assignments");
+        ok("fn f(x: u32) -> u32 { x.pow(2) }", "1.50", "`.pow()`");
+        ok("fn f(x: u8) -> u8 { match x { 0..=9 => x, _ => 0 } }", "1.46", "`match`");
+        ok("fn f(x: &mut u8) {}", "1.83", "mutable references");
+        ok("fn f<T: Copy>(x: T) -> T { x }", "1.61", "trait bounds");
+        ok("fn f<T>(x: T) -> T where T: Copy { x }", "1.61", "trait bounds");
+        ok("fn f(x: impl Copy) {}", "1.61", "`impl Trait`");
+        ok("fn f(x: fn()) {}", "1.61", "function pointers");
+        ok("fn f() -> f64 { 1.0 }", "1.82", "floating point arithmetic");
+        ok("fn f(x: (u8, (u8, u8))) -> u8 { x.1.0 }", "1.31", "`const fn`");
+        ok("fn f(x: bool) { assert!(x, \"msg\"); }", "1.57", "`assert!`");
+        ok("unsafe extern \"C\" fn f() {}", "1.62", "`extern \"C\" fn`");
+        ok("fn f() -> Vec<u8> { Vec::new() }", "1.39", "`Vec::new()`");
+        ok("fn f(x: &[u8]) -> Option<&u8> { x.first() }", "1.56", "`.first()`");
+        ok("fn f(x: &mut [u8; 2]) -> bool { x.is_empty() }", "1.83", "mutable references");
+        ok("fn f(x: Option<&str>) -> bool { x.is_some() }", "1.48", "`.is_some()`");
+        ok("fn f() -> u32 { let x = 1u8; x.count_ones() }", "1.33", "`let` bindings");
+        ok(
+            "fn f(x: u8) -> bool { let y = x as char; y.is_ascii_digit() }",
+            "1.47",
+            "`.is_ascii_digit()`",
+        );
+        ok("fn f<T: Copy>(x: T) -> usize { 0 }", "1.61", "trait bounds");
+        ok("fn f(x: (u8, [char; 2])) -> u8 { x.0 }", "1.31", "`const fn`");
+
+        let never = |src: &str, reason: &str| {
+            let (v, r, _) = requirement(src);
+            assert_eq!((&*v, &*r), ("-", reason), "{src}");
+        };
+        never("fn f(x: &[u8]) { for _ in x {} }", "`for` loops");
+        never("fn f(x: Option<u8>) -> Option<u8> { Some(x? + 1) }", "`?` operator");
+        never("async fn f() {}", "`async fn`");
+        never("fn f(x: u8) { panic!(\"{}\", x) }", "`panic!` with formatting arguments");
+        never("fn f() { println!() }", "`println!`");
+        never("fn f(x: &[u8]) -> &[u8] { &x[1..] }", "range indexing");
+        never(
+            "fn f(v: Vec<u8>) -> usize { v.len() }",
+            "by-value parameters that may have destructors",
+        );
+        never("fn f<T>(x: T) {}", "by-value parameters that may have destructors");
+        never("fn f(self) {}", "by-value parameters that may have destructors");
+        never("fn f(x: Option<String>) {}", "by-value parameters that may have destructors");
+        never(
+            "fn f() { let v: Vec<u8> = Vec::new(); }",
+            "local variables that may have destructors",
+        );
+        never("fn f() { let s = String::new(); }", "local variables that may have destructors");
+
+        let (_, _, calls) = requirement("fn f(x: &S) -> u8 { Self::g(x.h()) + i(x.len()) }");
+        assert_eq!(calls, ["g", "h", "i", "len"]);
+        // methods of unknown receivers
+        let (_, _, calls) =
+            requirement("fn f(s: &String, t: &S) -> bool { s.is_empty() && t.0.is_empty() }");
+        assert_eq!(calls, ["is_empty", "is_empty"]);
+    }
+}
@@ -0,0 +1,72 @@
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+/*!
+Cargo subcommand to inspect `#[const_fn]` attributes in a workspace.
+
+```text
+cargo const-fn audit [OPTIONS] [PATH]
+cargo const-fn migrate [--dry-run] [PATH]
+cargo const-fn candidates [OPTIONS] [PATH]
+```
+
+The arguments of the attributes are parsed by
+[`const_fn_core`](https://docs.rs/const_fn_core), so they are interpreted in the
+same way as by `#[const_fn]` itself.
+*/
+
+#![forbid(unsafe_code)]
+
+mod audit;
+mod candidates;
+mod constructs;
+mod metadata;
+mod migrate;
+mod report;
+mod scan;
+
+use std::{env, process};
+
+const USAGE: &str = "\
+Cargo subcommand to inspect #[const_fn] attributes in a workspace
+
+USAGE:
+    cargo const-fn <SUBCOMMAND> [OPTIONS]
+
+SUBCOMMANDS:
+    audit         Report every #[const_fn] function and its condition
+    migrate       Replace #[const_fn(\"1.XX\")] that always holds on rust-version with `const`
+    candidates    List non-const functions that could be `const fn`
+
+Run `cargo const-fn <SUBCOMMAND> --help` for more information on a subcommand.
+";
+
+fn main() {
+    if let Err(e) = try_main() {
+        eprintln!("error: {e}");
+        process::exit(1);
+    }
+}
+
+fn try_main() -> Result<(), String> {
+    let mut args = env::args().skip(1).peekable();
+    // `cargo const-fn` runs `cargo-const-fn const-fn`.
+    if args.peek().map(String::as_str) == Some("const-fn") {
+        args.next();
+    }
+    match args.next().as_deref() {
+        Some("audit") => audit::run(args),
+        Some("migrate") => migrate::run(args),
+        Some("candidates") => candidates::run(args),
+        Some("-h" | "--help") => {
+            print!("{USAGE}");
+            Ok(())
+        }
+        Some(cmd) => Err(format!("unknown subcommand `{cmd}`\n\n{USAGE}")),
+        None => Err(format!("no subcommand specified\n\n{USAGE}")),
+    }
+}
+
+// Returns the value of the `flag` option.
+fn value(args: &mut impl Iterator<Item = String>, flag: &str) -> Result<String, String> {
+    args.next().ok_or_else(|| format!("`{flag}` requires a value"))
+}
@@ -0,0 +1,139 @@
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+// Reads the packages of a workspace with `cargo metadata`.
+
+use std::{
+    env, fmt,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use serde_json::Value;
+
+pub(crate) struct Package {
+    pub(crate) name: String,
+    // The directory containing Cargo.toml.
+    pub(crate) dir: PathBuf,
+    // package.rust-version, resolved by cargo if it is inherited from the
+    // workspace.
+    pub(crate) rust_version: Option<RustVersion>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct RustVersion {
+    pub(crate) major: u32,
+    pub(crate) minor: u32,
+}
+
+impl RustVersion {
+    // Parses `1.XX` or `1.XX.YY`.
+    pub(crate) fn parse(s: &str) -> Result<Self, String> {
+        let mut pieces = s.split('.');
+        let mut next = |name: &str| match pieces.next() {
+            Some(n) => n.parse::<u32>().map_err(|e| format!("invalid Rust version `{s}`: {e}")),
+            None => Err(format!("invalid Rust version `{s}`: need to specify the {name} version")),
+        };
+        let version = Self { major: next("major")?, minor: next("minor")? };
+        if pieces.next().is_some_and(|patch| patch.parse::<u32>().is_err())
+            || pieces.next().is_some()
+        {
+            return Err(format!("invalid Rust version `{s}`"));
+        }
+        Ok(version)
+    }
+}
+
+impl fmt::Display for RustVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+// Returns the members of the workspace that contains `dir`.
+pub(crate) fn packages(dir: &Path) -> Result<Vec<Package>, String> {
+    let cargo = env::var_os("CARGO").unwrap_or_else(|| "cargo".into());
+    let output = Command::new(cargo)
+        .args(["metadata", "--format-version", "1", "--no-deps"])
+        .current_dir(dir)
+        .output()
+        .map_err(|e| format!("failed to run `cargo metadata`: {e}"))?;
+    if !output.status.success() {
+        return Err(format!(
+            "`cargo metadata` failed in {}:\n{}",
+            dir.display(),
+            String::from_utf8_lossy(&output.stderr).trim_end()
+        ));
+    }
+    parse(&output.stdout)
+}
+
+fn parse(json: &[u8]) -> Result<Vec<Package>, String> {
+    let invalid = || "invalid output of `cargo metadata`".to_owned();
+    let metadata: Value = serde_json::from_slice(json)
+        .map_err(|e| format!("failed to parse the output of `cargo metadata`: {e}"))?;
+    let mut packages = vec![];
+    for package in metadata["packages"].as_array().ok_or_else(invalid)? {
+        let name = package["name"].as_str().ok_or_else(invalid)?;
+        let manifest_path = Path::new(package["manifest_path"].as_str().ok_or_else(invalid)?);
+        let rust_version = match package["rust_version"].as_str() {
+            Some(v) => Some(
+                RustVersion::parse(v)
+                    .map_err(|e| format!("failed to parse {}: {}", manifest_path.display(), e))?,
+            ),
+            None => None,
+        };
+        packages.push(Package {
+            name: name.to_owned(),
+            dir: manifest_path.parent().ok_or_else(invalid)?.to_owned(),
+            rust_version,
+        });
+    }
+    Ok(packages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_metadata() {
+        let packages = parse(
+            br#"{
+  "packages": [
+    { "name": "a", "manifest_path": "/ws/Cargo.toml", "rust_version": "1.56" },
+    { "name": "b", "manifest_path": "/ws/b/Cargo.toml", "rust_version": null }
+  ],
+  "workspace_root": "/ws"
+}"#,
+        )
+        .unwrap();
+        let packages: Vec<_> = packages
+            .iter()
+            .map(|p| {
+                format!(
+                    "{} {} {}",
+                    p.name,
+                    p.dir.display(),
+                    p.rust_version.map_or("-".to_owned(), |v| v.to_string())
+                )
+            })
+            .collect();
+        assert_eq!(packages, ["a /ws 1.56", "b /ws/b -"]);
+
+        assert!(parse(b"{}").is_err());
+        let invalid_version =
+            br#"{"packages": [{"name": "a", "manifest_path": "/a/Cargo.toml", "rust_version": "1"}]}"#;
+        assert!(parse(invalid_version).is_err());
+    }
+
+    #[test]
+    fn rust_version() {
+        let v = |s| RustVersion::parse(s).map(|v| v.to_string());
+        assert_eq!(v("1.61").unwrap(), "1.61");
+        assert_eq!(v("1.61.1").unwrap(), "1.61");
+        assert!(v("1").is_err());
+        assert!(v("1.x").is_err());
+        assert!(v("1.61.0.0").is_err());
+        assert!(v("1.61.0-nightly").is_err());
+    }
+}
@@ -0,0 +1,415 @@
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+// `cargo const-fn migrate`: replaces `#[const_fn("1.XX")]` that always holds on
+// the `rust-version` of the package with `const` keyword, and removes
+// `use const_fn::const_fn;` that is no longer used.
+
+use std::{
+    fmt::Write as _,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use const_fn_core::Condition;
+use proc_macro2::TokenTree;
+
+use crate::{
+    metadata::RustVersion,
+    report,
+    scan::{self, ConstFn, Imports},
+};
+
+const USAGE: &str = "\
+Replaces #[const_fn(\"1.XX\")] attributes that always hold on the rust-version of
+the package with `const` keyword, and removes `use const_fn::const_fn;` that is
+no longer used
+
+USAGE:
+    cargo const-fn migrate [OPTIONS] [PATH]
+
+ARGS:
+    <PATH>    Directory of the package or workspace to migrate [default: .]
+
+OPTIONS:
+        --dry-run    Print the changes as a diff instead of writing them
+    -h, --help       Print help information
+";
+
+// Replaces lines `old_start..old_start + old_len` (0-based) with `new`.
+struct Change {
+    old_start: usize,
+    old_len: usize,
+    new: String,
+}
+
+pub(crate) fn run(args: impl Iterator<Item = String>) -> Result<(), String> {
+    let mut path = None;
+    let mut dry_run = false;
+    for arg in args {
+        match &*arg {
+            "--dry-run" => dry_run = true,
+            "-h" | "--help" => {
+                print!("{USAGE}");
+                return Ok(());
+            }
+            _ if arg.starts_with('-') => return Err(format!("unknown option `{arg}`")),
+            _ if path.is_none() => path = Some(PathBuf::from(arg)),
+            _ => return Err(format!("unexpected argument `{arg}`")),
+        }
+    }
+    let root = path.unwrap_or_else(|| PathBuf::from("."));
+
+    let mut count = 0;
+    for package in scan::packages(&root)? {
+        let Some(msrv) = package.rust_version else {
+            eprintln!("warning: skipped package `{}` without `rust-version`", package.name);
+            continue;
+        };
+        for file in &package.files {
+            let src = fs::read_to_string(file)
+                .map_err(|e| format!("failed to read {}: {}", file.display(), e))?;
+            let tokens = scan::parse_file(file)?;
+            let fns: Vec<_> = scan::const_fns(tokens.clone())
+                .into_iter()
+                .filter(|f| is_obsolete(f, &package.dir, msrv))
+                .collect();
+            if fns.is_empty() {
+                continue;
+            }
+            count += fns.len();
+            let (changes, unused_import) = changes(&src, &fns, &scan::imports(tokens));
+            if unused_import {
+                eprintln!(
+                    "warning: `use const_fn::const_fn;` in {} is no longer used",
+                    file.display()
+                );
+            }
+            if dry_run {
+                print!("{}", diff(file.strip_prefix(&root).unwrap_or(file), &src, &changes));
+            } else {
+                fs::write(file, apply(&src, &changes))
+                    .map_err(|e| format!("failed to write {}: {}", file.display(), e))?;
+            }
+        }
+    }
+    if !dry_run {
+        eprintln!("migrated {}", report::count(count, "function"));
+    }
+    Ok(())
+}
+
+// Returns `true` if the attribute only has a version that is always satisfied,
+// and is not behind `cfg_attr`.
+fn is_obsolete(f: &ConstFn, dir: &Path, msrv: RustVersion) -> bool {
+    if f.cfg_attr.is_some() {
+        return false;
+    }
+    let mut args = f.args.clone().into_iter();
+    match (args.next(), args.next()) {
+        (Some(TokenTree::Literal(_)), None) => {}
+        _ => return false,
+    }
+    match const_fn_core::proc_macro2::parse_condition(f.args.clone(), dir) {
+        Ok(Condition::Version { major, minor }) => RustVersion { major, minor } <= msrv,
+        _ => false,
+    }
+}
+
+// Returns the changes, and whether `use const_fn::const_fn;` that is no longer
+// used is left because it cannot be removed as a line.
+fn changes(src: &str, fns: &[ConstFn], imports: &Imports) -> (Vec<Change>, bool) {
+    let line_starts: Vec<_> = Some(0)
+        .into_iter()
+        .chain(src.match_indices('\n').map(|(i, _)| i + 1))
+        .filter(|&i| i < src.len())
+        .collect();
+    let line_of = |pos: usize| match line_starts.binary_search(&pos) {
+        Ok(line) => line,
+        Err(line) => line - 1,
+    };
+    let line_end = |line: usize| line_starts.get(line + 1).copied().unwrap_or(src.len());
+
+    let mut changes: Vec<Change> = vec![];
+    // Byte ranges of the removed attributes.
+    let mut removed = vec![];
+    for f in fns {
+        let attr_first = line_of(f.attr_range.start);
+        let attr_last = line_of(f.attr_range.end - 1);
+        if changes.last().is_some_and(|c| c.old_start + c.old_len > attr_first) {
+            // Multiple functions on the same line; leave them as is.
+            continue;
+        }
+        removed.push(f.attr_range.clone());
+        let own_line = src[line_starts[attr_first]..f.attr_range.start].trim().is_empty()
+            && src[f.attr_range.end..line_end(attr_last)].trim().is_empty();
+        if own_line {
+            // Remove the lines of the attribute, and insert `const` to the
+            // line of the function.
+            changes.push(Change {
+                old_start: attr_first,
+                old_len: attr_last - attr_first + 1,
+                new: String::new(),
+            });
+            if let Some(pos) = f.const_pos {
+                let line = line_of(pos);
+                let start = line_starts[line];
+                let mut new = src[start..line_end(line)].to_owned();
+                new.insert_str(pos - start, "const ");
+                changes.push(Change { old_start: line, old_len: 1, new });
+            }
+        } else {
+            let last = f.const_pos.map_or(attr_last, |pos| line_of(pos).max(attr_last));
+            let start = line_starts[attr_first];
+            let mut new = src[start..line_end(last)].to_owned();
+            // `const` is inserted after the attribute, so this doesn't change
+            // the offsets of the attribute.
+            if let Some(pos) = f.const_pos {
+                new.insert_str(pos - start, "const ");
+            }
+            let (attr_start, attr_end) = (f.attr_range.start - start, f.attr_range.end - start);
+            let rest = &new[attr_end..];
+            let spaces = rest.len() - rest.trim_start_matches([' ', '\t']).len();
+            new.replace_range(attr_start..attr_end + spaces, "");
+            changes.push(Change { old_start: attr_first, old_len: last - attr_first + 1, new });
+        }
+    }
+
+    // Remove `use const_fn::const_fn;` if the removed attributes were the
+    // last uses of it.
+    let mut unused_import = false;
+    if imports.uses.iter().all(|pos| removed.iter().any(|r| r.contains(pos))) {
+        for item in &imports.items {
+            let line = line_of(item.start);
+            let own_line = line == line_of(item.end - 1)
+                && src[line_starts[line]..line_end(line)].trim() == &src[item.clone()];
+            if !own_line
+                || changes.iter().any(|c| (c.old_start..c.old_start + c.old_len).contains(&line))
+            {
+                unused_import = true;
+                continue;
+            }
+            // Also remove the following blank line if the import is at the
+            // beginning of a block of lines.
+            let is_blank = |line: usize| {
+                line_starts
+                    .get(line)
+                    .is_some_and(|&start| src[start..line_end(line)].trim().is_empty())
+            };
+            let old_len =
+                if (line == 0 || is_blank(line - 1)) && is_blank(line + 1) { 2 } else { 1 };
+            changes.push(Change { old_start: line, old_len, new: String::new() });
+        }
+        changes.sort_by_key(|c| c.old_start);
+    }
+    (changes, unused_import)
+}
+
+fn apply(src: &str, changes: &[Change]) -> String {
+    let lines: Vec<_> = src.split_inclusive('\n').collect();
+    let mut out = String::with_capacity(src.len());
+    let mut line = 0;
+    for c in changes {
+        out.push_str(&lines[line..c.old_start].concat());
+        out.push_str(&c.new);
+        line = c.old_start + c.old_len;
+    }
+    out.push_str(&lines[line..].concat());
+    out
+}
+
+// Unified diff of the changes, with 3 lines of context.
+fn diff(path: &Path, src: &str, changes: &[Change]) -> String {
+    const CONTEXT: usize = 3;
+    let lines: Vec<_> = src.split_inclusive('\n').collect();
+    let path = path.display().to_string().replace('\\', "/");
+    let mut out = format!("--- a/{path}\n+++ b/{path}\n");
+    // The number of lines in the previous hunks of the old and new file, to
+    // compute the line numbers in the new file.
+    let (mut old_total, mut new_total) = (0, 0);
+    let mut i = 0;
+    while i < changes.len() {
+        // Merge changes whose contexts overlap into one hunk.
+        let mut j = i + 1;
+        while j < changes.len()
+            && changes[j].old_start
+                <= changes[j - 1].old_start + changes[j - 1].old_len + CONTEXT * 2
+        {
+            j += 1;
+        }
+        let hunk = &changes[i..j];
+        let old_start = hunk[0].old_start.saturating_sub(CONTEXT);
+        let last = &hunk[hunk.len() - 1];
+        let old_end = (last.old_start + last.old_len + CONTEXT).min(lines.len());
+
+        let mut body = String::new();
+        let mut new_len = 0;
+        let mut line = old_start;
+        for c in hunk {
+            for l in &lines[line..c.old_start] {
+                push_line(&mut body, ' ', l);
+                new_len += 1;
+            }
+            for l in &lines[c.old_start..c.old_start + c.old_len] {
+                push_line(&mut body, '-', l);
+            }
+            for l in c.new.split_inclusive('\n') {
+                push_line(&mut body, '+', l);
+                new_len += 1;
+            }
+            line = c.old_start + c.old_len;
+        }
+        for l in &lines[line..old_end] {
+            push_line(&mut body, ' ', l);
+            new_len += 1;
+        }
+        let old_len = old_end - old_start;
+        // The previous hunks are before `old_start`, so this doesn't underflow.
+        let new_start = old_start + new_total - old_total;
+        let _ = writeln!(
+            out,
+            "@@ -{},{} +{},{} @@",
+            old_start + 1,
+            old_len,
+            if new_len == 0 { new_start } else { new_start + 1 },
+            new_len
+        );
+        out.push_str(&body);
+        old_total += old_len;
+        new_total += new_len;
+        i = j;
+    }
+    out
+}
+
+fn push_line(out: &mut String, prefix: char, line: &str) {
+    out.push(prefix);
+    out.push_str(line);
+    if !line.ends_with('\n') {
+        out.push_str("\n\\ No newline at end of file\n");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proc_macro2::TokenStream;
+
+    use super::*;
+
+    const SRC: &str = r#"use const_fn::const_fn;
+
+/// Doc comment.
+#[const_fn("1.31")]
+#[inline]
+pub fn a() {} // comment
+
+#[const_fn("1.61")]
+fn b() {}
+
+#[const_fn("1.36")] const unsafe fn c() {}
+
+impl S {
+    #[const_fn(  "1.40"  )]  pub(crate)  fn d(&self) {}
+    #[const_fn("1.40", export_flag)]
+    pub fn e() {}
+}
+
+#[cfg_attr(feature = "a", const_fn("1.31"))]
+fn f() {}
+"#;
+
+    fn migrate(src: &str) -> Vec<Change> {
+        let msrv = RustVersion { major: 1, minor: 56 };
+        let tokens: TokenStream = src.parse().unwrap();
+        let fns: Vec<_> = scan::const_fns(tokens.clone())
+            .into_iter()
+            .filter(|f| is_obsolete(f, Path::new("."), msrv))
+            .collect();
+        changes(src, &fns, &scan::imports(tokens)).0
+    }
+
+    #[test]
+    fn apply_changes() {
+        assert_eq!(
+            apply(SRC, &migrate(SRC)),
+            r#"use const_fn::const_fn;
+
+/// Doc comment.
+#[inline]
+pub const fn a() {} // comment
+
+#[const_fn("1.61")]
+fn b() {}
+
+const unsafe fn c() {}
+
+impl S {
+    pub(crate)  const fn d(&self) {}
+    #[const_fn("1.40", export_flag)]
+    pub fn e() {}
+}
+
+#[cfg_attr(feature = "a", const_fn("1.31"))]
+fn f() {}
+"#
+        );
+        // no trailing newline
+        let src = "#[const_fn(\"1.31\")]\nfn a() {}";
+        assert_eq!(apply(src, &migrate(src)), "const fn a() {}");
+    }
+
+    #[test]
+    fn remove_unused_import() {
+        let src = "use const_fn::const_fn;\n\n#[const_fn(\"1.31\")]\nfn a() {}\n";
+        assert_eq!(apply(src, &migrate(src)), "const fn a() {}\n");
+        let src = "//! Doc.\nuse const_fn::const_fn;\nuse std::fmt;\n\n#[const_fn(\"1.31\")]\nfn a() {}\n";
+        assert_eq!(apply(src, &migrate(src)), "//! Doc.\nuse std::fmt;\n\nconst fn a() {}\n");
+        // still used
+        let src = "use const_fn::const_fn;\n#[const_fn(\"1.31\")]\nfn a() {}\n#[cfg_attr(a, const_fn)]\nfn b() {}\n";
+        assert_eq!(
+            apply(src, &migrate(src)),
+            "use const_fn::const_fn;\nconst fn a() {}\n#[cfg_attr(a, const_fn)]\nfn b() {}\n"
+        );
+        // not on its own line, or not an import of the attribute
+        for src in [
+            "use const_fn::const_fn; use std::fmt;\n#[const_fn(\"1.31\")]\nfn a() {}\n",
+            "pub use const_fn::const_fn;\n#[const_fn(\"1.31\")]\nfn a() {}\n",
+        ] {
+            let tokens: TokenStream = src.parse().unwrap();
+            let fns = scan::const_fns(tokens.clone());
+            let (changes, unused_import) = changes(src, &fns, &scan::imports(tokens));
+            assert_eq!(changes.len(), 2);
+            assert_eq!(unused_import, !src.starts_with("pub"));
+        }
+    }
+
+    #[test]
+    fn diff_changes() {
+        let expected = [
+            "--- a/src/lib.rs",
+            "+++ b/src/lib.rs",
+            "@@ -1,17 +1,16 @@",
+            " use const_fn::const_fn;",
+            " ",
+            " /// Doc comment.",
+            "-#[const_fn(\"1.31\")]",
+            " #[inline]",
+            "-pub fn a() {} // comment",
+            "+pub const fn a() {} // comment",
+            " ",
+            " #[const_fn(\"1.61\")]",
+            " fn b() {}",
+            " ",
+            "-#[const_fn(\"1.36\")] const unsafe fn c() {}",
+            "+const unsafe fn c() {}",
+            " ",
+            " impl S {",
+            "-    #[const_fn(  \"1.40\"  )]  pub(crate)  fn d(&self) {}",
+            "+    pub(crate)  const fn d(&self) {}",
+            "     #[const_fn(\"1.40\", export_flag)]",
+            "     pub fn e() {}",
+            " }",
+            "",
+        ];
+        assert_eq!(diff(Path::new("src/lib.rs"), SRC, &migrate(SRC)), expected.join("\n"));
+    }
+}
@@ -0,0 +1,49 @@
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+// Helpers for the output of subcommands.
+
+use std::fmt::Write as _;
+
+// The value of `--format` option.
+pub(crate) enum Format {
+    Table,
+    Json,
+}
+
+impl Format {
+    pub(crate) fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "table" => Ok(Self::Table),
+            "json" => Ok(Self::Json),
+            _ => Err(format!("unknown format `{s}`; expected `table` or `json`")),
+        }
+    }
+}
+
+// Formats rows as left-aligned columns. The first row is the header.
+pub(crate) fn columns(rows: &[Vec<String>]) -> String {
+    let mut widths = vec![0; rows.first().map_or(0, Vec::len)];
+    for row in rows {
+        for (w, cell) in widths.iter_mut().zip(row) {
+            *w = (*w).max(cell.chars().count());
+        }
+    }
+    let mut out = String::new();
+    for row in rows {
+        let mut line = String::new();
+        for (i, (cell, w)) in row.iter().zip(&widths).enumerate() {
+            if i != 0 {
+                line.push_str("  ");
+            }
+            let _ = write!(line, "{cell:w$}");
+        }
+        out.push_str(line.trim_end());
+        out.push('\n');
+    }
+    out
+}
+
+// `1 function`, `2 functions`, etc.
+pub(crate) fn count(n: usize, noun: &str) -> String {
+    if n == 1 { format!("{n} {noun}") } else { format!("{n} {noun}s") }
+}
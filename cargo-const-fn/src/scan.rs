@@ -0,0 +1,644 @@
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+// Finds packages, source files, and functions in them.
+
+use std::{
+    fs, mem,
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+use proc_macro2::{Delimiter, TokenStream, TokenTree};
+
+use crate::metadata::{self, RustVersion};
+
+pub(crate) struct Package {
+    pub(crate) name: String,
+    pub(crate) dir: PathBuf,
+    pub(crate) rust_version: Option<RustVersion>,
+    // Rust source files in the package directory, excluding nested packages.
+    pub(crate) files: Vec<PathBuf>,
+}
+
+// Finds the packages of the workspace in `root` and its subdirectories.
+pub(crate) fn packages(root: &Path) -> Result<Vec<Package>, String> {
+    let canonicalize = |path: &Path| fs::canonicalize(path).unwrap_or_else(|_| path.to_owned());
+    let root_dir = canonicalize(root);
+    let mut all = metadata::packages(root)?;
+    for package in &mut all {
+        package.dir = canonicalize(&package.dir);
+    }
+    all.sort_by(|a, b| a.dir.cmp(&b.dir));
+    let dirs: Vec<_> = all.iter().map(|p| p.dir.clone()).collect();
+    let mut packages = vec![];
+    for package in all {
+        // Keep the paths relative to `root` as given, to report them as is.
+        let dir = match package.dir.strip_prefix(&root_dir) {
+            Ok(rel) => root.join(rel),
+            Err(_) => continue,
+        };
+        let mut files = vec![];
+        walk(&package.dir, &dirs, &mut files)?;
+        let files = files
+            .into_iter()
+            .map(|f| f.strip_prefix(&package.dir).map_or(f.clone(), |rel| dir.join(rel)))
+            .collect();
+        packages.push(Package {
+            name: package.name,
+            dir,
+            rust_version: package.rust_version,
+            files,
+        });
+    }
+    if packages.is_empty() {
+        return Err(format!("could not find any package in {}", root.display()));
+    }
+    Ok(packages)
+}
+
+// Collects Rust source files in `dir`, skipping hidden directories, `target`,
+// symbolic links to directories, and the directories of other packages.
+fn walk(dir: &Path, package_dirs: &[PathBuf], files: &mut Vec<PathBuf>) -> Result<(), String> {
+    let mut entries = vec![];
+    for entry in
+        fs::read_dir(dir).map_err(|e| format!("failed to read {}: {}", dir.display(), e))?
+    {
+        let entry = entry.map_err(|e| format!("failed to read {}: {}", dir.display(), e))?;
+        let file_type =
+            entry.file_type().map_err(|e| format!("failed to read {}: {}", dir.display(), e))?;
+        entries.push((entry.path(), file_type));
+    }
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
+    for (path, file_type) in entries {
+        let name = path.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+        if file_type.is_dir() {
+            if name.starts_with('.') || name == "target" || package_dirs.contains(&path) {
+                continue;
+            }
+            walk(&path, package_dirs, files)?;
+        } else if file_type.is_symlink() {
+            // Symbolic links to directories may form cycles or point outside
+            // of the package, so only follow links to files.
+            if path.is_file() && path.extension().is_some_and(|e| e == "rs") {
+                files.push(path);
+            }
+        } else if path.extension().is_some_and(|e| e == "rs") {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn parse_file(path: &Path) -> Result<TokenStream, String> {
+    let s = fs::read_to_string(path)
+        .map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+    s.parse().map_err(|e| format!("failed to parse {}: {}", path.display(), e))
+}
+
+// A function with `#[const_fn]` attribute.
+pub(crate) struct ConstFn {
+    // 1-based line of the attribute.
+    pub(crate) line: usize,
+    // Byte range of the attribute, from `#` to `]`.
+    pub(crate) attr_range: Range<usize>,
+    pub(crate) name: String,
+    // `pub`, `pub(crate)`, etc., or empty.
+    pub(crate) vis: String,
+    // Byte offset where `const` keyword should be inserted, or `None` if the
+    // function is already written as `const fn`.
+    pub(crate) const_pos: Option<usize>,
+    // The arguments of the attribute, without parentheses.
+    pub(crate) args: TokenStream,
+    // The arguments as written in the source code.
+    pub(crate) args_text: String,
+    // The predicate if the attribute is behind `cfg_attr`.
+    pub(crate) cfg_attr: Option<String>,
+}
+
+pub(crate) fn const_fns(tokens: TokenStream) -> Vec<ConstFn> {
+    let mut fns = vec![];
+    collect(tokens, &mut fns);
+    fns
+}
+
+fn collect(tokens: TokenStream, fns: &mut Vec<ConstFn>) {
+    let tokens: Vec<_> = tokens.into_iter().collect();
+    let mut i = 0;
+    while i < tokens.len() {
+        match &tokens[i] {
+            // Skip `macro_rules! name { ... }`, which may contain `#[const_fn]`
+            // with metavariables.
+            TokenTree::Ident(ident) if ident == "macro_rules" => i += 3,
+            TokenTree::Punct(p) if p.as_char() == '#' => {
+                if let Some(TokenTree::Group(g)) = tokens.get(i + 1) {
+                    if g.delimiter() == Delimiter::Bracket {
+                        if let Some(attr) = const_fn_attr(g.stream()) {
+                            if let Some(sig) = parse_fn(&tokens[i + 2..]) {
+                                fns.push(ConstFn {
+                                    line: p.span().start().line,
+                                    attr_range: p.span().byte_range().start
+                                        ..g.span().byte_range().end,
+                                    name: sig.name,
+                                    vis: sig.vis,
+                                    const_pos: if sig.is_const { None } else { Some(sig.start) },
+                                    args: attr.args,
+                                    args_text: attr.args_text,
+                                    cfg_attr: attr.cfg_attr,
+                                });
+                            }
+                        }
+                    }
+                }
+            }
+            TokenTree::Group(g) => collect(g.stream(), fns),
+            _ => {}
+        }
+        i += 1;
+    }
+}
+
+// A `#[const_fn]` attribute.
+pub(crate) struct ConstFnAttr {
+    // The arguments of the attribute, without parentheses.
+    pub(crate) args: TokenStream,
+    // The arguments as written in the source code.
+    pub(crate) args_text: String,
+    // The predicate if the attribute is behind `cfg_attr`, e.g., `a` for
+    // `#[cfg_attr(a, const_fn)]`.
+    pub(crate) cfg_attr: Option<String>,
+}
+
+// Returns the attribute if it is `const_fn`, `const_fn(...)`, or
+// `const_fn::const_fn(...)`, optionally behind `cfg_attr`.
+fn const_fn_attr(attr: TokenStream) -> Option<ConstFnAttr> {
+    let mut tokens = attr.clone().into_iter();
+    if let (Some(TokenTree::Ident(i)), Some(TokenTree::Group(g)), None) =
+        (tokens.next(), tokens.next(), tokens.next())
+    {
+        if i == "cfg_attr" && g.delimiter() == Delimiter::Parenthesis {
+            return cfg_attr(g.stream());
+        }
+    }
+    let (args, args_text) = const_fn_args(attr)?;
+    Some(ConstFnAttr { args, args_text, cfg_attr: None })
+}
+
+// `<pred>, <attr>, ...` in `cfg_attr(...)`.
+fn cfg_attr(tokens: TokenStream) -> Option<ConstFnAttr> {
+    let mut pieces = vec![TokenStream::new()];
+    for tt in tokens {
+        match tt {
+            TokenTree::Punct(ref p) if p.as_char() == ',' => pieces.push(TokenStream::new()),
+            _ => pieces.last_mut().unwrap().extend(Some(tt)),
+        }
+    }
+    let pred = pieces.remove(0).to_string();
+    let mut attr = pieces.into_iter().find_map(const_fn_attr)?;
+    attr.cfg_attr = Some(match attr.cfg_attr {
+        Some(inner) => format!("all({pred}, {inner})"),
+        None => pred,
+    });
+    Some(attr)
+}
+
+fn const_fn_args(attr: TokenStream) -> Option<(TokenStream, String)> {
+    let mut last_ident = None;
+    for tt in attr {
+        match tt {
+            TokenTree::Ident(i) => last_ident = Some(i),
+            TokenTree::Punct(ref p) if p.as_char() == ':' => {}
+            TokenTree::Group(g) if g.delimiter() == Delimiter::Parenthesis => {
+                return if last_ident.is_some_and(|i| i == "const_fn") {
+                    let text = g.span().source_text().unwrap_or_else(|| g.to_string());
+                    Some((g.stream(), text[1..text.len() - 1].trim().to_owned()))
+                } else {
+                    None
+                };
+            }
+            _ => return None,
+        }
+    }
+    if last_ident.is_some_and(|i| i == "const_fn") {
+        Some((TokenStream::new(), String::new()))
+    } else {
+        None
+    }
+}
+
+struct Signature {
+    name: String,
+    vis: String,
+    // Whether `const` keyword is written.
+    is_const: bool,
+    // Byte offset of the first token after the visibility.
+    start: usize,
+}
+
+// Parses `[#[...]]* [vis] [qualifiers] fn <name>`.
+fn parse_fn(tokens: &[TokenTree]) -> Option<Signature> {
+    let mut iter = tokens.iter().peekable();
+    // other attributes
+    while let Some(TokenTree::Punct(p)) = iter.peek() {
+        if p.as_char() != '#' {
+            return None;
+        }
+        iter.next();
+        iter.next();
+    }
+    let mut vis = String::new();
+    if let Some(TokenTree::Ident(i)) = iter.peek() {
+        if i == "pub" {
+            vis.push_str("pub");
+            iter.next();
+            if let Some(TokenTree::Group(g)) = iter.peek() {
+                if g.delimiter() == Delimiter::Parenthesis {
+                    vis.push('(');
+                    vis.push_str(&g.stream().to_string());
+                    vis.push(')');
+                    iter.next();
+                }
+            }
+        }
+    }
+    let start = iter.peek()?.span().byte_range().start;
+    let mut is_const = false;
+    // `const`, `async`, `unsafe`, `extern "C"`
+    loop {
+        match iter.next()? {
+            TokenTree::Ident(i) if i == "fn" => break,
+            TokenTree::Ident(i) if i == "const" => is_const = true,
+            TokenTree::Ident(_) | TokenTree::Literal(_) => {}
+            _ => return None,
+        }
+    }
+    match iter.next()? {
+        TokenTree::Ident(i) => {
+            let name = i.to_string();
+            let name = name.strip_prefix("r#").map_or(name.clone(), str::to_owned);
+            Some(Signature { name, vis, is_const, start })
+        }
+        _ => None,
+    }
+}
+
+// Where a function is defined.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Context {
+    Module,
+    Impl,
+    TraitImpl,
+    Trait,
+}
+
+// A function with a body.
+pub(crate) struct Function {
+    // 1-based line of the name.
+    pub(crate) line: usize,
+    pub(crate) name: String,
+    pub(crate) context: Context,
+    // The paths of the attributes, e.g., `inline` or `const_fn::const_fn`.
+    pub(crate) attrs: Vec<String>,
+    // `#[const_fn]` attribute, if any.
+    pub(crate) const_fn: Option<ConstFnAttr>,
+    // Keywords and the ABI before `fn`, e.g., `const`, `unsafe`, `extern`, `"C"`.
+    pub(crate) qualifiers: Vec<String>,
+    // The generics, parameters, return type, and where clause.
+    pub(crate) sig: Vec<TokenTree>,
+    pub(crate) body: TokenStream,
+}
+
+// Finds the functions in modules, impls, and traits. Functions in
+// `#[cfg(test)]` modules and in function bodies are ignored.
+pub(crate) fn functions(tokens: TokenStream) -> Vec<Function> {
+    let mut fns = vec![];
+    collect_functions(tokens, Context::Module, &mut fns);
+    fns
+}
+
+fn collect_functions(tokens: TokenStream, cx: Context, fns: &mut Vec<Function>) {
+    let tokens: Vec<_> = tokens.into_iter().collect();
+    // The state of the current item.
+    let mut attrs = vec![];
+    let mut const_fn = None;
+    let mut cfg_test = false;
+    let mut qualifiers = vec![];
+    let mut kind = None;
+    let mut i = 0;
+    while i < tokens.len() {
+        match &tokens[i] {
+            TokenTree::Punct(p) if p.as_char() == '#' => {
+                // `#[...]` or `#![...]`
+                if matches!(tokens.get(i + 1), Some(TokenTree::Punct(p)) if p.as_char() == '!') {
+                    i += 1;
+                } else if let Some(TokenTree::Group(g)) = tokens.get(i + 1) {
+                    cfg_test |= g.stream().to_string().replace(' ', "") == "cfg(test)";
+                    attrs.push(attr_path(g.stream()));
+                    if let Some(attr) = const_fn_attr(g.stream()) {
+                        const_fn = Some(attr);
+                    }
+                }
+                i += 2;
+                continue;
+            }
+            TokenTree::Ident(ident) if ident == "fn" => {
+                if let Some(TokenTree::Ident(name)) = tokens.get(i + 1) {
+                    let mut sig = vec![];
+                    let mut body = None;
+                    i += 2;
+                    while let Some(tt) = tokens.get(i) {
+                        match tt {
+                            TokenTree::Group(g) if g.delimiter() == Delimiter::Brace => {
+                                body = Some(g.stream());
+                                break;
+                            }
+                            TokenTree::Punct(p) if p.as_char() == ';' => break,
+                            _ => sig.push(tt.clone()),
+                        }
+                        i += 1;
+                    }
+                    if let Some(body) = body {
+                        let line = name.span().start().line;
+                        let name = name.to_string();
+                        fns.push(Function {
+                            line,
+                            name: name.strip_prefix("r#").map_or(name.clone(), str::to_owned),
+                            context: cx,
+                            attrs: mem::take(&mut attrs),
+                            const_fn: const_fn.take(),
+                            qualifiers: mem::take(&mut qualifiers),
+                            sig,
+                            body,
+                        });
+                    }
+                } else {
+                    // `fn` pointer type in `type` or `static` items.
+                    i += 1;
+                    continue;
+                }
+            }
+            TokenTree::Ident(ident) => {
+                match &*ident.to_string() {
+                    "impl" => kind = Some(Context::Impl),
+                    "for" if kind == Some(Context::Impl) => kind = Some(Context::TraitImpl),
+                    "trait" => kind = Some(Context::Trait),
+                    "mod" => kind = Some(Context::Module),
+                    "pub" => {}
+                    q => qualifiers.push(q.to_owned()),
+                }
+                i += 1;
+                continue;
+            }
+            TokenTree::Literal(lit) if kind.is_none() => {
+                qualifiers.push(lit.to_string());
+                i += 1;
+                continue;
+            }
+            TokenTree::Group(g) if g.delimiter() == Delimiter::Brace => {
+                if let Some(kind) = kind {
+                    if !cfg_test {
+                        collect_functions(g.stream(), kind, fns);
+                    }
+                }
+            }
+            TokenTree::Punct(p) if p.as_char() == ';' => {}
+            _ => {
+                i += 1;
+                continue;
+            }
+        }
+        // The end of an item.
+        attrs.clear();
+        const_fn = None;
+        cfg_test = false;
+        qualifiers.clear();
+        kind = None;
+        i += 1;
+    }
+}
+
+// Returns the path of an attribute, e.g., `inline` for `#[inline(always)]`.
+fn attr_path(attr: TokenStream) -> String {
+    let mut path = String::new();
+    for tt in attr {
+        match tt {
+            TokenTree::Ident(i) => path.push_str(&i.to_string()),
+            TokenTree::Punct(ref p) if p.as_char() == ':' => path.push(':'),
+            _ => break,
+        }
+    }
+    path
+}
+
+// `use const_fn::const_fn;` items and the uses of the imported name.
+#[derive(Default)]
+pub(crate) struct Imports {
+    // Byte ranges of the items, from `use` to `;`.
+    pub(crate) items: Vec<Range<usize>>,
+    // Byte offsets of `const_fn` that are not part of a path, e.g., in
+    // `#[const_fn]`.
+    pub(crate) uses: Vec<usize>,
+}
+
+pub(crate) fn imports(tokens: TokenStream) -> Imports {
+    let mut imports = Imports::default();
+    collect_imports(tokens, &mut imports);
+    imports
+}
+
+fn collect_imports(tokens: TokenStream, imports: &mut Imports) {
+    let tokens: Vec<_> = tokens.into_iter().collect();
+    let is_punct = |tt: &TokenTree, c: char| matches!(tt, TokenTree::Punct(p) if p.as_char() == c);
+    let is_path_sep = |tt: Option<&TokenTree>| tt.is_some_and(|tt| is_punct(tt, ':'));
+    let mut i = 0;
+    while i < tokens.len() {
+        match &tokens[i] {
+            TokenTree::Ident(ident) if ident == "use" && is_import(&tokens[i..]) => {
+                // Imports with visibility or attributes are left as is.
+                let item_start = i == 0
+                    || match &tokens[i - 1] {
+                        TokenTree::Punct(p) => p.as_char() == ';',
+                        TokenTree::Group(g) => match g.delimiter() {
+                            Delimiter::Brace => true,
+                            // inner attributes, e.g., `//! ...`
+                            Delimiter::Bracket => i >= 2 && is_punct(&tokens[i - 2], '!'),
+                            _ => false,
+                        },
+                        _ => false,
+                    };
+                if item_start {
+                    let end = tokens[i + 5].span().byte_range().end;
+                    imports.items.push(ident.span().byte_range().start..end);
+                }
+                i += 6;
+                continue;
+            }
+            TokenTree::Ident(ident)
+                if ident == "const_fn"
+                    && !is_path_sep(i.checked_sub(1).and_then(|i| tokens.get(i)))
+                    && !is_path_sep(tokens.get(i + 1)) =>
+            {
+                imports.uses.push(ident.span().byte_range().start);
+            }
+            TokenTree::Group(g) => collect_imports(g.stream(), imports),
+            _ => {}
+        }
+        i += 1;
+    }
+}
+
+// Returns `true` if `tokens` starts with `use const_fn::const_fn;`.
+fn is_import(tokens: &[TokenTree]) -> bool {
+    let tokens: Vec<_> = tokens.iter().take(6).map(ToString::to_string).collect();
+    tokens == ["use", "const_fn", ":", ":", "const_fn", ";"]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_const_fns() {
+        let src = r#"
+#[const_fn]
+fn a() {}
+
+mod m {
+    #[const_fn("1.61")]
+    #[inline]
+    pub(crate) unsafe fn r#b() {}
+}
+
+impl S {
+    #[const_fn::const_fn( cfg(any(a, b)) )]
+    pub fn c() {}
+}
+
+#[const_fn(nightly)]
+struct D;
+
+macro_rules! e {
+    ($t:tt) => {
+        #[const_fn($t)]
+        fn e() {}
+    };
+}
+
+#[cfg_attr(a, const_fn)]
+fn f() {}
+
+#[const_fn("1.31")] pub const unsafe fn g() {}
+
+#[cfg_attr(a, inline, cfg_attr(feature = "b", const_fn::const_fn("1.61")))]
+fn h() {}
+"#;
+        let fns = const_fns(src.parse().unwrap());
+        let fns: Vec<_> = fns
+            .iter()
+            .map(|f| {
+                format!(
+                    "{}:{} {} [{}] {} {}{}",
+                    f.line,
+                    f.name,
+                    f.vis,
+                    f.args_text,
+                    &src[f.attr_range.clone()],
+                    f.const_pos.map_or("-", |p| src[p..].split(' ').next().unwrap()),
+                    f.cfg_attr.as_ref().map_or(String::new(), |p| format!(" if {p}")),
+                )
+            })
+            .collect();
+        assert_eq!(
+            fns,
+            [
+                "2:a  [] #[const_fn] fn",
+                "6:b pub(crate) [\"1.61\"] #[const_fn(\"1.61\")] unsafe",
+                "12:c pub [cfg(any(a, b))] #[const_fn::const_fn( cfg(any(a, b)) )] fn",
+                "26:f  [] #[cfg_attr(a, const_fn)] fn if a",
+                "29:g pub [\"1.31\"] #[const_fn(\"1.31\")] -",
+                "31:h  [\"1.61\"] #[cfg_attr(a, inline, cfg_attr(feature = \"b\", \
+                 const_fn::const_fn(\"1.61\")))] fn if all(a, feature = \"b\")",
+            ]
+        );
+    }
+
+    #[test]
+    fn find_functions() {
+        let src = r#"
+#![allow(a)]
+
+/// Doc comment.
+#[inline]
+pub(crate) const unsafe extern "C" fn a<T>(x: T) -> T { x }
+
+fn b();
+
+type F = fn();
+
+impl<T> S<T> where T: Copy {
+    fn c(&self) {}
+}
+
+impl Clone for S {
+    fn clone(&self) -> Self { fn d() {} *self }
+}
+
+trait T {
+    fn e() {}
+}
+
+mod m {
+    #[test]
+    fn f() {}
+}
+
+#[cfg(test)]
+mod tests {
+    fn g() {}
+}
+
+extern "C" {
+    fn h();
+}
+
+macro_rules! i {
+    () => { fn i() {} };
+}
+
+struct S { j: fn() }
+
+fn r#k() {}
+"#;
+        let fns = functions(src.parse().unwrap());
+        let fns: Vec<_> = fns
+            .iter()
+            .map(|f| {
+                let cx = match f.context {
+                    Context::Module => "mod",
+                    Context::Impl => "impl",
+                    Context::TraitImpl => "trait-impl",
+                    Context::Trait => "trait",
+                };
+                format!(
+                    "{}:{} {} [{}] [{}] [{}]",
+                    f.line,
+                    f.name,
+                    cx,
+                    f.attrs.join(", "),
+                    f.qualifiers.join(" "),
+                    f.sig.iter().map(ToString::to_string).collect::<Vec<_>>().join(" "),
+                )
+            })
+            .collect();
+        assert_eq!(
+            fns,
+            [
+                "6:a mod [doc, inline] [const unsafe extern \"C\"] [< T > (x : T) - > T]",
+                "13:c impl [] [] [(& self)]",
+                "17:clone trait-impl [] [] [(& self) - > Self]",
+                "21:e trait [] [] [()]",
+                "26:f mod [test] [] [()]",
+                "44:k mod [] [] [()]",
+            ]
+        );
+    }
+}
@@ -0,0 +1,529 @@
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+/*!
+Build script helper that detects the rustc version and channel in the same way
+as [`const_fn`](https://docs.rs/const_fn), and emits cfgs that pair with
+`#[const_fn(cfg(...))]`.
+
+## Examples
+
+```
+// build.rs
+println!("cargo:rerun-if-changed=build.rs");
+
+// Emits `rustc_1_61` and `rustc_1_83` cfgs when the compiler is Rust 1.61+
+// and Rust 1.83+ respectively, `nightly` cfg on nightly compilers,
+// and the corresponding `rustc-check-cfg` declarations.
+const_fn_build::Config::new().version("1.61").version("1.83").nightly("nightly").emit();
+```
+
+```
+// lib.rs
+# #[cfg(any(/* always false */))]
+#[const_fn::const_fn(cfg(rustc_1_61))]
+pub const fn f() {
+    /* ... */
+}
+```
+
+The nightly detection respects `RUSTC_BOOTSTRAP` (including the list of crate
+names) and `-Z allow-features` in rustflags.
+
+Note that [`Config::emit`] prints `cargo:rerun-if-env-changed` for the
+environment variables that affect the detection, so Cargo will not rerun the
+build script on changes to the package's files unless the build script also
+prints `cargo:rerun-if-changed`.
+*/
+
+#![doc(test(
+    no_crate_inject,
+    attr(allow(
+        dead_code,
+        unused_variables,
+        clippy::undocumented_unsafe_blocks,
+        clippy::unused_trait_names,
+    ))
+))]
+#![forbid(unsafe_code)]
+
+use std::{env, fmt, process::Command, str};
+
+/// A builder for the cfgs emitted by [`Config::emit`].
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    versions: Vec<(u32, u32)>,
+    prefix: Option<String>,
+    nightly: Option<String>,
+}
+
+impl Config {
+    /// Creates a new `Config`.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Emits `rustc_<major>_<minor>` cfg when the compiler is the specified
+    /// version or later, e.g., `rustc_1_61` for `"1.61"`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `version` is not in the form of `<major>.<minor>`.
+    pub fn version(&mut self, version: &str) -> &mut Self {
+        let req = parse_req(version).unwrap_or_else(|| {
+            panic!("invalid version {:?} (expected `<major>.<minor>`)", version)
+        });
+        self.versions.push(req);
+        self
+    }
+
+    /// Same as calling [`version`](Self::version) for each element of `versions`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any of `versions` is not in the form of `<major>.<minor>`.
+    pub fn versions(&mut self, versions: &[&str]) -> &mut Self {
+        for version in versions {
+            self.version(version);
+        }
+        self
+    }
+
+    /// Sets the prefix of version cfgs. The default is `rustc_`.
+    pub fn prefix(&mut self, prefix: &str) -> &mut Self {
+        self.prefix = Some(prefix.to_owned());
+        self
+    }
+
+    /// Emits cfg with the specified name on nightly compilers.
+    pub fn nightly(&mut self, name: &str) -> &mut Self {
+        self.nightly = Some(name.to_owned());
+        self
+    }
+
+    /// Detects the rustc version and prints the cfgs and `rustc-check-cfg`
+    /// declarations.
+    ///
+    /// If the rustc version cannot be determined, prints a warning and emits
+    /// only the `rustc-check-cfg` declarations.
+    pub fn emit(&self) {
+        rerun_if_env_changed();
+
+        let version = match rustc_version() {
+            Ok(version) => Some(version),
+            Err(e) => {
+                println!(
+                    "cargo:warning={}: unable to determine rustc version: {}",
+                    env!("CARGO_PKG_NAME"),
+                    e
+                );
+                None
+            }
+        };
+
+        let prefix = self.prefix.as_ref().map_or("rustc_", |s| &**s);
+        let names: Vec<_> = self
+            .versions
+            .iter()
+            .map(|&(major, minor)| format!("{}{}_{}", prefix, major, minor))
+            .chain(self.nightly.iter().cloned())
+            .collect();
+        // rustc-check-cfg is supported since Rust 1.80. If the version is
+        // unknown, emit it anyway so that uses of the cfgs are not reported as
+        // unexpected on recent compilers.
+        if version.as_ref().map_or(true, |v| v.minor >= 80) && !names.is_empty() {
+            println!("cargo:rustc-check-cfg=cfg({})", names.join(","));
+        }
+        let version = match version {
+            Some(version) => version,
+            None => return,
+        };
+
+        for (&(major, minor), name) in self.versions.iter().zip(&names) {
+            if major == 1 && minor <= version.minor {
+                println!("cargo:rustc-cfg={}", name);
+            }
+        }
+        if let Some(ref name) = self.nightly {
+            if version.is_nightly() {
+                println!("cargo:rustc-cfg={}", name);
+            }
+        }
+    }
+}
+
+fn parse_req(s: &str) -> Option<(u32, u32)> {
+    let mut pieces = s.split('.');
+    let major = pieces.next()?.parse().ok()?;
+    let minor = pieces.next()?.parse().ok()?;
+    if pieces.next().is_some() {
+        return None;
+    }
+    Some((major, minor))
+}
+
+/// Prints `cargo:rerun-if-env-changed` for the environment variables that
+/// affect the result of [`rustc_version`].
+pub fn rerun_if_env_changed() {
+    println!("cargo:rerun-if-env-changed=RUSTC_BOOTSTRAP");
+    println!("cargo:rerun-if-env-changed=RUSTFLAGS");
+    println!("cargo:rerun-if-env-changed=CARGO_ENCODED_RUSTFLAGS");
+}
+
+/// An error that occurred while detecting the rustc version.
+#[derive(Debug)]
+pub struct Error(String);
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Detects the version and channel of the compiler used to compile the
+/// current package.
+///
+/// This must be called from a build script.
+pub fn rustc_version() -> Result<Version, Error> {
+    let rustc = env::var_os("RUSTC").ok_or_else(|| Error("RUSTC not set".to_owned()))?;
+    let rustc_wrapper = if env::var_os("CARGO_ENCODED_RUSTFLAGS").is_some() {
+        env::var_os("RUSTC_WRAPPER").filter(|v| !v.is_empty())
+    } else {
+        // Cargo sets environment variables for wrappers correctly only since https://github.com/rust-lang/cargo/pull/9601.
+        None
+    };
+    // Do not apply RUSTC_WORKSPACE_WRAPPER: https://github.com/cuviper/autocfg/issues/58#issuecomment-2067625980
+    let mut cmd = match rustc_wrapper {
+        Some(rustc_wrapper) => {
+            let mut cmd = Command::new(rustc_wrapper);
+            cmd.arg(rustc);
+            cmd
+        }
+        None => Command::new(rustc),
+    };
+    // Use verbose version output because the packagers add extra strings to the normal version output.
+    // Do not use long flags (--version --verbose) because clippy-deriver doesn't handle them properly.
+    // -vV is also matched with that cargo internally uses: https://github.com/rust-lang/cargo/blob/0.80.0/src/cargo/util/rustc.rs#L65
+    let output = cmd
+        .arg("-vV")
+        .output()
+        .map_err(|e| Error(format!("could not execute {:?}: {}", cmd, e)))?;
+    let verbose_version = str::from_utf8(&output.stdout)
+        .map_err(|e| Error(format!("failed to parse output of {:?}: {}", cmd, e)))?;
+    Version::parse(verbose_version)
+        .ok_or_else(|| Error(format!("unexpected output from {:?}: {}", cmd, verbose_version)))
+}
+
+/// The version and channel of the compiler.
+#[derive(Debug, Clone)]
+pub struct Version {
+    minor: u32,
+    patch: u32,
+    // stable, beta, nightly, or dev
+    channel: String,
+    // The commit date of the nightly (or dev) compiler, in the form of YYYY-MM-DD.
+    nightly_date: Option<String>,
+    nightly: bool,
+    // Crates listed in `RUSTC_BOOTSTRAP=crate_a,crate_b`.
+    bootstrap_crates: Vec<String>,
+}
+
+impl Version {
+    fn parse(verbose_version: &str) -> Option<Self> {
+        let bootstrap = env::var("RUSTC_BOOTSTRAP").ok();
+        Self::parse_with(verbose_version, bootstrap.as_ref().map(|s| &**s), &unstable_flags())
+    }
+
+    // Parses the output of `rustc -vV` with the given value of `RUSTC_BOOTSTRAP`
+    // and the given `-Z` flags.
+    fn parse_with(
+        verbose_version: &str,
+        bootstrap: Option<&str>,
+        unstable_flags: &[String],
+    ) -> Option<Self> {
+        let mut release = verbose_version
+            .lines()
+            .find(|line| line.starts_with("release: "))
+            .map(|line| &line["release: ".len()..])?
+            .splitn(2, '-');
+        let version = release.next().unwrap();
+        // e.g., "beta.1" -> "beta"
+        let channel = release.next().unwrap_or("stable").split('.').next().unwrap();
+        let mut digits = version.splitn(3, '.');
+        let major = digits.next()?;
+        if major != "1" {
+            return None;
+        }
+        let minor = digits.next()?.parse::<u32>().ok()?;
+        let patch = digits.next().unwrap_or("0").parse::<u32>().ok()?;
+        let mut nightly = channel == "nightly" || channel == "dev";
+        let nightly_date = if nightly {
+            verbose_version
+                .lines()
+                .find(|line| line.starts_with("commit-date: "))
+                .map(|line| line["commit-date: ".len()..].trim())
+                .filter(|date| *date != "unknown")
+                .map(str::to_owned)
+        } else {
+            None
+        };
+        let mut bootstrap_crates = vec![];
+        match bootstrap {
+            // When -1 is passed rustc works like stable, e.g., cfg(target_feature = "unstable_target_feature") will never be set. https://github.com/rust-lang/rust/pull/132993
+            Some("-1") => nightly = false,
+            // When 1 is passed rustc works like nightly.
+            Some("1") => nightly = true,
+            // When a comma-separated list of crate names is passed, rustc works
+            // like nightly only when compiling the listed crates.
+            Some(v) => {
+                bootstrap_crates = v
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|s| !s.is_empty())
+                    .map(str::to_owned)
+                    .collect();
+            }
+            None => {}
+        }
+        // -Z allow-features= (with empty list) disallows all unstable features.
+        // Note that we cannot know which unstable features are actually used,
+        // so a non-empty list is not considered here.
+        if unstable_flags.iter().any(|flag| flag == "allow-features=") {
+            nightly = false;
+            bootstrap_crates.clear();
+        }
+
+        Some(Self {
+            minor,
+            patch,
+            channel: channel.to_owned(),
+            nightly_date,
+            nightly,
+            bootstrap_crates,
+        })
+    }
+
+    /// Returns the minor version, e.g., `83` for Rust 1.83.0.
+    #[must_use]
+    pub fn minor(&self) -> u32 {
+        self.minor
+    }
+
+    /// Returns the patch version, e.g., `0` for Rust 1.83.0.
+    #[must_use]
+    pub fn patch(&self) -> u32 {
+        self.patch
+    }
+
+    /// Returns the release channel: `stable`, `beta`, `nightly`, or `dev`.
+    #[must_use]
+    pub fn channel(&self) -> &str {
+        &self.channel
+    }
+
+    /// Returns the commit date of the nightly (or dev) compiler, in the form
+    /// of `YYYY-MM-DD`.
+    #[must_use]
+    pub fn nightly_date(&self) -> Option<&str> {
+        self.nightly_date.as_ref().map(|s| &**s)
+    }
+
+    /// Returns `true` if unstable features are available for all crates.
+    ///
+    /// This is `true` on nightly compilers or with `RUSTC_BOOTSTRAP=1`,
+    /// and `false` with `RUSTC_BOOTSTRAP=-1` or `-Z allow-features=`.
+    #[must_use]
+    pub fn nightly(&self) -> bool {
+        self.nightly
+    }
+
+    /// Returns the crate names listed in `RUSTC_BOOTSTRAP=crate_a,crate_b`.
+    /// Unstable features are available when compiling these crates.
+    #[must_use]
+    pub fn bootstrap_crates(&self) -> &[String] {
+        &self.bootstrap_crates
+    }
+
+    /// Returns `true` if unstable features are available when compiling the
+    /// current crate.
+    ///
+    /// The crate name is read from `CARGO_CRATE_NAME`. Cargo does not set it
+    /// for build scripts, so in build scripts the default name of the library
+    /// target (the package name with `-` replaced by `_`) is used. If the
+    /// library target is renamed, check [`bootstrap_crates`](Self::bootstrap_crates)
+    /// instead.
+    #[must_use]
+    pub fn is_nightly(&self) -> bool {
+        let name = env::var("CARGO_CRATE_NAME")
+            .or_else(|_| env::var("CARGO_PKG_NAME").map(|name| name.replace('-', "_")));
+        self.is_nightly_for(name.as_ref().ok().map(|s| &**s))
+    }
+
+    fn is_nightly_for(&self, crate_name: Option<&str>) -> bool {
+        self.nightly
+            || crate_name.map_or(false, |name| self.bootstrap_crates.iter().any(|c| c == name))
+    }
+}
+
+/// Returns the values of `-Z` flags in rustflags, e.g., `["allow-features=", "assume-incomplete-release"]`
+/// for `-Z allow-features= -Zassume-incomplete-release`.
+///
+/// This respects `CARGO_ENCODED_RUSTFLAGS`, or `RUSTFLAGS` on Cargo older than 1.55.
+#[must_use]
+pub fn unstable_flags() -> Vec<String> {
+    parse_unstable_flags(rustflags())
+}
+
+fn parse_unstable_flags(rustflags: Vec<String>) -> Vec<String> {
+    let mut flags = vec![];
+    let mut rustflags = rustflags.into_iter();
+    while let Some(flag) = rustflags.next() {
+        if flag == "-Z" {
+            if let Some(flag) = rustflags.next() {
+                flags.push(flag);
+            }
+        } else if flag.starts_with("-Z") {
+            flags.push(flag["-Z".len()..].to_owned());
+        }
+    }
+    flags
+}
+
+fn rustflags() -> Vec<String> {
+    // https://github.com/rust-lang/cargo/issues/10111
+    if let Some(rustflags) = env::var_os("CARGO_ENCODED_RUSTFLAGS") {
+        return rustflags
+            .to_string_lossy()
+            .split('\x1f')
+            .filter(|s| !s.is_empty())
+            .map(str::to_owned)
+            .collect();
+    }
+    // Cargo sets CARGO_ENCODED_RUSTFLAGS only since Rust 1.55: https://github.com/rust-lang/cargo/pull/9601
+    if let Some(rustflags) = env::var_os("RUSTFLAGS") {
+        return rustflags.to_string_lossy().split_whitespace().map(str::to_owned).collect();
+    }
+    vec![]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STABLE: &str = "rustc 1.83.0 (90b35a623 2024-11-26)
+binary: rustc
+commit-hash: 90b35a6239c3d8bdabc530a6a0816f7ff89a0aaf
+commit-date: 2024-11-26
+host: x86_64-unknown-linux-gnu
+release: 1.83.0
+LLVM version: 19.1.1
+";
+    const BETA: &str = "rustc 1.84.0-beta.1 (8dc83770f 2024-11-25)
+commit-date: 2024-11-25
+release: 1.84.0-beta.1
+";
+    const NIGHTLY: &str = "rustc 1.85.0-nightly (7db7489f9 2024-11-25)
+commit-hash: 7db7489f9bc274cb60c4956bfa56de0185eb1b9b
+commit-date: 2024-11-25
+release: 1.85.0-nightly
+";
+
+    fn flags(flags: &[&str]) -> Vec<String> {
+        flags.iter().map(|&s| s.to_owned()).collect()
+    }
+
+    #[test]
+    fn version_parse() {
+        let v = Version::parse_with(STABLE, None, &[]).unwrap();
+        assert_eq!((v.minor(), v.patch(), v.channel()), (83, 0, "stable"));
+        assert_eq!(v.nightly_date(), None);
+        assert!(!v.nightly());
+
+        let v = Version::parse_with(BETA, None, &[]).unwrap();
+        assert_eq!((v.minor(), v.patch(), v.channel()), (84, 0, "beta"));
+        assert_eq!(v.nightly_date(), None);
+        assert!(!v.nightly());
+
+        let v = Version::parse_with(NIGHTLY, None, &[]).unwrap();
+        assert_eq!((v.minor(), v.patch(), v.channel()), (85, 0, "nightly"));
+        assert_eq!(v.nightly_date(), Some("2024-11-25"));
+        assert!(v.nightly());
+
+        let v =
+            Version::parse_with("release: 1.86.0-dev\ncommit-date: unknown\n", None, &[]).unwrap();
+        assert_eq!((v.minor(), v.channel(), v.nightly_date()), (86, "dev", None));
+        assert!(v.nightly());
+
+        // Packagers add extra strings to the normal version output.
+        let v = Version::parse_with(
+            "rustc 1.82.1 (Fedora 1.82.1-1.fc41)\nrelease: 1.82.1\n",
+            None,
+            &[],
+        )
+        .unwrap();
+        assert_eq!((v.minor(), v.patch()), (82, 1));
+        let v = Version::parse_with("release: 1.31\n", None, &[]).unwrap();
+        assert_eq!((v.minor(), v.patch()), (31, 0));
+
+        assert!(Version::parse_with("rustc 1.83.0 (90b35a623 2024-11-26)\n", None, &[]).is_none());
+        assert!(Version::parse_with("release: 2.0.0\n", None, &[]).is_none());
+        assert!(Version::parse_with("release: 1.x.0\n", None, &[]).is_none());
+        assert!(Version::parse_with("", None, &[]).is_none());
+    }
+
+    #[test]
+    fn version_bootstrap() {
+        let v = Version::parse_with(STABLE, Some("1"), &[]).unwrap();
+        assert!(v.nightly());
+        let v = Version::parse_with(NIGHTLY, Some("-1"), &[]).unwrap();
+        assert!(!v.nightly());
+
+        let v = Version::parse_with(STABLE, Some("foo, bar-baz,"), &[]).unwrap();
+        assert!(!v.nightly());
+        assert_eq!(v.bootstrap_crates(), ["foo", "bar-baz"]);
+        assert!(v.is_nightly_for(Some("foo")));
+        assert!(!v.is_nightly_for(Some("bar")));
+        assert!(!v.is_nightly_for(None));
+
+        // -Z allow-features= disallows all unstable features.
+        let allow_none = flags(&["allow-features="]);
+        let v = Version::parse_with(NIGHTLY, None, &allow_none).unwrap();
+        assert!(!v.nightly());
+        let v = Version::parse_with(STABLE, Some("foo"), &allow_none).unwrap();
+        assert!(!v.is_nightly_for(Some("foo")));
+        let v = Version::parse_with(NIGHTLY, None, &flags(&["allow-features=const_trait_impl"]))
+            .unwrap();
+        assert!(v.nightly());
+    }
+
+    #[test]
+    fn unstable_flags() {
+        assert_eq!(super::parse_unstable_flags(vec![]), Vec::<String>::new());
+        assert_eq!(
+            super::parse_unstable_flags(flags(&[
+                "-Z",
+                "allow-features=",
+                "-Zassume-incomplete-release",
+                "--cfg",
+                "foo",
+                "-C",
+                "opt-level=3",
+            ])),
+            ["allow-features=", "assume-incomplete-release"]
+        );
+        // Trailing `-Z` without a value.
+        assert_eq!(super::parse_unstable_flags(flags(&["-Zfoo", "-Z"])), ["foo"]);
+    }
+
+    #[test]
+    fn parse_req() {
+        assert_eq!(super::parse_req("1.61"), Some((1, 61)));
+        assert_eq!(super::parse_req("1.0"), Some((1, 0)));
+        assert_eq!(super::parse_req("1"), None);
+        assert_eq!(super::parse_req("1.61.0"), None);
+        assert_eq!(super::parse_req("1.x"), None);
+        assert_eq!(super::parse_req(""), None);
+    }
+}
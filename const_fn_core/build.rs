@@ -0,0 +1,96 @@
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+// The rustc-cfg emitted by the build script are *not* public API.
+
+use std::{env, fs, path::PathBuf};
+
+use const_fn_build::Version;
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-env-changed=CONST_FN_FORCE");
+    // Read by `option_env!` in src/imp/debug.rs.
+    println!("cargo:rerun-if-env-changed=CONST_FN_DEBUG");
+    // Read by src/imp/mod.rs when the version cannot be determined.
+    println!("cargo:rerun-if-env-changed=CONST_FN_RUSTC_VERSION");
+    const_fn_build::rerun_if_env_changed();
+
+    let version = match const_fn_build::rustc_version() {
+        Ok(version) => Some(version),
+        Err(e) => {
+            if env::var_os("CONST_FN_DENY_WARNINGS").is_some() {
+                panic!("unable to determine rustc version")
+            }
+            println!(
+                "cargo:warning={}: unable to determine rustc version: {}",
+                env!("CARGO_PKG_NAME"),
+                e
+            );
+            None
+        }
+    };
+
+    // Emit check-cfg also when the version is unknown: Cargo ignores it on
+    // compilers that do not support it.
+    if version.as_ref().map_or(true, |v| v.minor() >= 80) {
+        println!(
+            "cargo:rustc-check-cfg=cfg(const_fn_assume_incomplete_release,const_fn_has_build_script)"
+        );
+        println!(r#"cargo:rustc-check-cfg=cfg(host_os,values("windows"))"#);
+        println!(r#"cargo:rustc-check-cfg=cfg(const_fn_force,values("always","never"))"#);
+    }
+
+    // Global override of all conditions.
+    // This is useful for coverage builds (const-evaluated calls are not instrumented)
+    // and for debugging const evaluation errors.
+    match env::var("CONST_FN_FORCE") {
+        Ok(ref v) if v == "always" || v == "never" => {
+            println!(r#"cargo:rustc-cfg=const_fn_force="{}""#, v);
+        }
+        Ok(ref v) if v == "auto" || v.is_empty() => {}
+        Ok(v) => println!(
+            "cargo:warning={}: unknown value of CONST_FN_FORCE: {:?} (expected `always`, `never`, or `auto`)",
+            env!("CARGO_PKG_NAME"),
+            v
+        ),
+        Err(_) => {}
+    }
+
+    let host = env::var("HOST").expect("HOST not set");
+    if host.contains("-windows") {
+        println!(r#"cargo:rustc-cfg=host_os="windows""#);
+    }
+
+    let version = match version {
+        Some(version) => version,
+        // src/imp/mod.rs falls back to CONST_FN_RUSTC_VERSION or Rust 1.31.
+        None => return,
+    };
+
+    let out_dir: PathBuf = env::var_os("OUT_DIR").expect("OUT_DIR not set").into();
+    let out_file = &out_dir.join("version");
+    fs::write(out_file, print(&version))
+        .unwrap_or_else(|e| panic!("failed to write {}: {}", out_file.display(), e));
+
+    // https://github.com/taiki-e/const_fn/issues/27
+    // https://github.com/rust-lang/rust/pull/81468
+    // Recognized formats: -Z *assume-incomplete-release
+    if const_fn_build::unstable_flags().iter().any(|flag| flag == "assume-incomplete-release") {
+        println!("cargo:rustc-cfg=const_fn_assume_incomplete_release");
+    }
+
+    // Mark as build script has been run successfully.
+    println!("cargo:rustc-cfg=const_fn_has_build_script");
+}
+
+// The crate being compiled is only known when the macro expands, so pass the
+// list of crates in RUSTC_BOOTSTRAP to the proc-macro.
+fn print(version: &Version) -> String {
+    format!(
+        "Version {{ minor: {}, patch: {}, nightly: {}, bootstrap_crates: &{:?} }}\n",
+        version.minor(),
+        version.patch(),
+        version.nightly(),
+        version.bootstrap_crates()
+    )
+}
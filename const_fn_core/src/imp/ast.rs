@@ -0,0 +1,528 @@
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+use super::pm::{Delimiter, Group, Ident, Literal, Punct, Spacing, Span, TokenStream, TokenTree};
+
+use super::{Error, Result, iter::TokenIter, to_tokens::ToTokens, utils::tt_span};
+
+pub(crate) struct Func {
+    attrs: Vec<Attribute>,
+    // [const] [async] [unsafe] [extern [<abi>]] fn
+    sig: Vec<TokenTree>,
+    body: TokenStream,
+    pub(crate) print_const: bool,
+    // `with(...)`: attributes only on the const variant.
+    pub(crate) const_attrs: Vec<Attribute>,
+    // `without(...)`: attributes only on the non-const variant.
+    pub(crate) non_const_attrs: Vec<Attribute>,
+}
+
+pub(crate) fn parse_input(input: TokenStream, attr: &str) -> Result<Func> {
+    let input = &mut TokenIter::new(input);
+
+    let attrs = parse_attrs(input)?;
+    let sig = parse_signature(input);
+    let body: TokenStream = input.collect();
+
+    if body.is_empty()
+        || !sig
+            .iter()
+            .any(|tt| if let TokenTree::Ident(i) = tt { i.to_string() == "fn" } else { false })
+    {
+        let item = item_keyword(&sig);
+        match item {
+            Some(TokenTree::Ident(i)) if i.to_string() == "static" && attr == "const_fn" => bail!(
+                i.span(),
+                "#[const_fn] attribute may only be used on functions; \
+                 use #[const_or_lazy] attribute for statics"
+            ),
+            _ => bail!(tt_span(item), "#[{}] attribute may only be used on functions", attr),
+        }
+    }
+
+    Ok(Func { attrs, sig, body, print_const: true, const_attrs: vec![], non_const_attrs: vec![] })
+}
+
+impl Func {
+    // The name of the function, without `r#` prefix.
+    pub(crate) fn name(&self) -> Result<String> {
+        match self.body.clone().into_iter().next() {
+            Some(TokenTree::Ident(i)) => {
+                let name = i.to_string();
+                Ok(if name.starts_with("r#") { name["r#".len()..].to_owned() } else { name })
+            }
+            tt => bail!(tt_span(tt.as_ref()), "expected identifier"),
+        }
+    }
+
+    // Appends `#[doc = ""] #[doc = "<doc>"]` to the attributes if the function
+    // has doc comments. (Adding documentation to undocumented functions would
+    // silence `missing_docs`.)
+    pub(crate) fn push_doc(&mut self, doc: &str) {
+        if !self.attrs.iter().any(Attribute::is_doc_comment) {
+            return;
+        }
+        for doc in &["", doc] {
+            let mut tokens: TokenStream = "doc =".parse().unwrap();
+            tokens.extend(Some(TokenTree::Literal(Literal::string(doc))));
+            self.attrs.push(Attribute {
+                pound_token: TokenTree::Punct(Punct::new('#', Spacing::Alone)),
+                group: TokenTree::Group(Group::new(Delimiter::Bracket, tokens)),
+            });
+        }
+    }
+
+    // `pub`, `pub(...)`, or empty.
+    pub(crate) fn vis(&self) -> TokenStream {
+        let mut tokens = TokenStream::new();
+        match self.sig.first() {
+            Some(tt @ TokenTree::Ident(_)) if tt.to_string() == "pub" => {
+                tt.to_tokens(&mut tokens);
+                if let Some(g @ TokenTree::Group(_)) = self.sig.get(1) {
+                    g.to_tokens(&mut tokens);
+                }
+            }
+            _ => {}
+        }
+        tokens
+    }
+
+    // `pub` token if the function is public (`pub(...)` is not considered public).
+    pub(crate) fn pub_token(&self) -> Option<&TokenTree> {
+        match self.sig.first() {
+            Some(tt @ TokenTree::Ident(_)) if tt.to_string() == "pub" => match self.sig.get(1) {
+                Some(TokenTree::Group(_)) => None,
+                _ => Some(tt),
+            },
+            _ => None,
+        }
+    }
+
+    // A private copy of the function named `__const_fn_check_<name>`, with only
+    // `cfg` and lint attributes.
+    pub(crate) fn check_copy(&self) -> Result<Self> {
+        let attrs = self
+            .attrs
+            .iter()
+            .filter(|attr| {
+                ["cfg", "allow", "warn", "deny", "forbid", "expect"]
+                    .iter()
+                    .any(|name| attr.path_is(name))
+            })
+            .cloned()
+            .collect();
+        let sig = self.sig[self.vis().into_iter().count()..].to_vec();
+        let mut body = self.body.clone().into_iter();
+        let span = tt_span(body.next().as_ref());
+        let name = Ident::new(&format!("__const_fn_check_{}", self.name()?), span);
+        let body = Some(TokenTree::Ident(name)).into_iter().chain(body).collect();
+        Ok(Self {
+            attrs,
+            sig,
+            body,
+            print_const: self.print_const,
+            const_attrs: self.const_attrs.clone(),
+            non_const_attrs: self.non_const_attrs.clone(),
+        })
+    }
+
+    // `{ ... }` of the function.
+    pub(crate) fn block(&self) -> Result<TokenTree> {
+        match self.body.clone().into_iter().last() {
+            Some(TokenTree::Group(ref g)) if g.delimiter() == Delimiter::Brace => {
+                Ok(TokenTree::Group(g.clone()))
+            }
+            tt => bail!(tt_span(tt.as_ref()), "expected `{`"),
+        }
+    }
+
+    // Inserts `tokens` at the start of `{ ... }` of the function.
+    pub(crate) fn prepend_to_block(&mut self, tokens: TokenStream) {
+        let mut body: Vec<_> = self.body.clone().into_iter().collect();
+        if let Some(TokenTree::Group(g)) = body.last_mut() {
+            let mut stream = tokens;
+            stream.extend(g.stream());
+            let mut group = Group::new(g.delimiter(), stream);
+            group.set_span(g.span());
+            *g = group;
+        }
+        self.body = body.into_iter().collect();
+    }
+
+    // `#[cfg(...)]` attributes of the function.
+    pub(crate) fn cfg_attrs(&self) -> TokenStream {
+        let mut tokens = TokenStream::new();
+        for attr in &self.attrs {
+            if attr.path_is("cfg") {
+                attr.to_tokens(&mut tokens);
+            }
+        }
+        tokens
+    }
+}
+
+impl ToTokens for Func {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let attrs = if self.print_const { &self.const_attrs } else { &self.non_const_attrs };
+        self.attrs.iter().chain(attrs).for_each(|attr| attr.to_tokens(tokens));
+        if self.print_const {
+            self.sig.iter().for_each(|attr| attr.to_tokens(tokens));
+        } else {
+            self.sig
+                .iter()
+                .filter(
+                    |tt| if let TokenTree::Ident(i) = tt { i.to_string() != "const" } else { true },
+                )
+                .for_each(|tt| tt.to_tokens(tokens));
+        }
+        self.body.to_tokens(tokens);
+    }
+}
+
+pub(crate) struct Static {
+    attrs: Vec<Attribute>,
+    // [pub [(...)]]
+    pub(crate) vis: TokenStream,
+    // static <name>: <ty> = <expr>;
+    static_token: TokenTree,
+    pub(crate) name: Ident,
+    pub(crate) ty: TokenStream,
+    pub(crate) expr: TokenStream,
+}
+
+pub(crate) fn parse_static(input: TokenStream) -> Result<Static> {
+    let input = &mut TokenIter::new(input);
+
+    let attrs = parse_attrs(input)?;
+    let mut vis = TokenStream::new();
+    let static_token = loop {
+        match input.next() {
+            Some(TokenTree::Ident(ref i)) if i.to_string() == "static" => {
+                break TokenTree::Ident(i.clone());
+            }
+            Some(tt) => tt.to_tokens(&mut vis),
+            None => {
+                let tokens: Vec<_> = vis.into_iter().collect();
+                bail!(
+                    tt_span(item_keyword(&tokens)),
+                    "#[const_or_lazy] attribute may only be used on statics"
+                )
+            }
+        }
+    };
+    let name = match input.next() {
+        Some(TokenTree::Ident(ref i)) if i.to_string() == "mut" => {
+            bail!(i.span(), "#[const_or_lazy] attribute cannot be used on `static mut`")
+        }
+        Some(TokenTree::Ident(i)) => i,
+        tt => bail!(tt_span(tt.as_ref()), "expected identifier"),
+    };
+    match input.next() {
+        Some(TokenTree::Punct(ref p)) if p.as_char() == ':' => {}
+        tt => bail!(tt_span(tt.as_ref()), "expected `:`"),
+    }
+    let mut ty = TokenStream::new();
+    // `<` `>` nesting, to skip `=` in `Iterator<Item = T>`.
+    let mut depth = 0_usize;
+    let mut prev_is_minus = false;
+    loop {
+        let tt = match input.next() {
+            Some(tt) => tt,
+            None => bail!(Span::call_site(), "expected `=`"),
+        };
+        let mut is_minus = false;
+        if let TokenTree::Punct(ref p) = tt {
+            match p.as_char() {
+                '=' if depth == 0 => break,
+                ';' => bail!(
+                    p.span(),
+                    "expected `=`; #[const_or_lazy] attribute requires an initializer expression"
+                ),
+                '<' => depth += 1,
+                // `->` in `fn() -> T`
+                '>' if !prev_is_minus => depth = depth.saturating_sub(1),
+                '-' => is_minus = true,
+                _ => {}
+            }
+        }
+        prev_is_minus = is_minus;
+        tt.to_tokens(&mut ty);
+    }
+    let mut expr: Vec<TokenTree> = input.collect();
+    match expr.pop() {
+        Some(TokenTree::Punct(ref p)) if p.as_char() == ';' => {}
+        tt => bail!(tt_span(tt.as_ref()), "expected `;`"),
+    }
+    if ty.is_empty() || expr.is_empty() {
+        bail!(name.span(), "expected `static {}: <type> = <expr>;`", name);
+    }
+
+    Ok(Static { attrs, vis, static_token, name, ty, expr: expr.into_iter().collect() })
+}
+
+impl Static {
+    // `#[cfg(...)]` attributes of the static.
+    pub(crate) fn cfg_attrs(&self) -> TokenStream {
+        let mut tokens = TokenStream::new();
+        for attr in &self.attrs {
+            if attr.path_is("cfg") {
+                attr.to_tokens(&mut tokens);
+            }
+        }
+        tokens
+    }
+
+    // `<attrs> <vis> static`
+    pub(crate) fn head(&self) -> TokenStream {
+        let mut tokens = TokenStream::new();
+        self.attrs.iter().for_each(|attr| attr.to_tokens(&mut tokens));
+        self.vis.to_tokens(&mut tokens);
+        self.static_token.to_tokens(&mut tokens);
+        tokens
+    }
+}
+
+impl ToTokens for Static {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        self.head().to_tokens(tokens);
+        TokenTree::Ident(self.name.clone()).to_tokens(tokens);
+        tokens.extend(":".parse::<TokenStream>().unwrap());
+        self.ty.to_tokens(tokens);
+        tokens.extend("=".parse::<TokenStream>().unwrap());
+        self.expr.to_tokens(tokens);
+        tokens.extend(";".parse::<TokenStream>().unwrap());
+    }
+}
+
+fn parse_signature(input: &mut TokenIter) -> Vec<TokenTree> {
+    let mut sig = vec![];
+    let mut has_const = false;
+    loop {
+        match input.peek() {
+            None => break,
+            Some(TokenTree::Ident(i)) if !has_const => {
+                match &*i.to_string() {
+                    "fn" => {
+                        sig.push(TokenTree::Ident(Ident::new("const", i.span())));
+                        sig.push(input.next().unwrap());
+                        break;
+                    }
+                    "const" => {
+                        has_const = true;
+                    }
+                    "async" | "unsafe" | "extern" => {
+                        has_const = true;
+                        sig.push(TokenTree::Ident(Ident::new("const", i.span())));
+                    }
+                    _ => {}
+                }
+                sig.push(input.next().unwrap());
+            }
+            Some(TokenTree::Ident(i)) if i.to_string() == "fn" => {
+                sig.push(input.next().unwrap());
+                break;
+            }
+            Some(_) => sig.push(input.next().unwrap()),
+        }
+    }
+    sig
+}
+
+fn parse_attrs(input: &mut TokenIter) -> Result<Vec<Attribute>> {
+    let mut attrs = vec![];
+    loop {
+        let pound_token = match input.peek() {
+            Some(TokenTree::Punct(p)) if p.as_char() == '#' => input.next().unwrap(),
+            _ => break,
+        };
+        let group = match input.peek() {
+            Some(TokenTree::Group(g)) if g.delimiter() == Delimiter::Bracket => {
+                input.next().unwrap()
+            }
+            tt => bail!(tt_span(tt), "expected `[`"),
+        };
+        attrs.push(Attribute { pound_token, group });
+    }
+    Ok(attrs)
+}
+
+// Parses `#[...], #[...]` in `with(...)` and `without(...)` options.
+pub(crate) fn parse_attr_list(tokens: TokenStream) -> Result<Vec<Attribute>> {
+    let input = &mut TokenIter::new(tokens);
+    let mut attrs = vec![];
+    loop {
+        let pound_token = match input.next() {
+            Some(TokenTree::Punct(p)) if p.as_char() == '#' => TokenTree::Punct(p),
+            None => break,
+            tt => bail!(tt_span(tt.as_ref()), "expected attribute like `#[...]`"),
+        };
+        let group = match input.next() {
+            Some(TokenTree::Group(g)) if g.delimiter() == Delimiter::Bracket => TokenTree::Group(g),
+            tt => bail!(tt_span(tt.as_ref()), "expected `[`"),
+        };
+        attrs.push(Attribute { pound_token, group });
+        match input.next() {
+            Some(TokenTree::Punct(ref p)) if p.as_char() == ',' => {}
+            None => break,
+            tt => bail!(tt_span(tt.as_ref()), "expected `,`"),
+        }
+    }
+    Ok(attrs)
+}
+
+#[derive(Clone)]
+pub(crate) struct Attribute {
+    // `#`
+    pub(crate) pound_token: TokenTree,
+    // `[...]`
+    pub(crate) group: TokenTree,
+}
+
+impl Attribute {
+    pub(crate) fn path_is(&self, name: &str) -> bool {
+        match &self.group {
+            TokenTree::Group(g) => match g.stream().into_iter().next() {
+                Some(TokenTree::Ident(ref i)) => i.to_string() == name,
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    // `#[doc = ...]`, i.e., a doc comment.
+    fn is_doc_comment(&self) -> bool {
+        match &self.group {
+            TokenTree::Group(g) => {
+                let mut iter = g.stream().into_iter();
+                match (iter.next(), iter.next()) {
+                    (Some(TokenTree::Ident(ref i)), Some(TokenTree::Punct(ref p))) => {
+                        i.to_string() == "doc" && p.as_char() == '='
+                    }
+                    _ => false,
+                }
+            }
+            _ => false,
+        }
+    }
+}
+
+impl ToTokens for Attribute {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        self.pound_token.to_tokens(tokens);
+        self.group.to_tokens(tokens);
+    }
+}
+
+// The first token after the visibility (e.g., `fn`, `struct`), used as the
+// span of "may only be used on" errors.
+fn item_keyword(tokens: &[TokenTree]) -> Option<&TokenTree> {
+    let vis_len = match tokens.first() {
+        Some(TokenTree::Ident(i)) if i.to_string() == "pub" => match tokens.get(1) {
+            Some(TokenTree::Group(g)) if g.delimiter() == Delimiter::Parenthesis => 2,
+            _ => 1,
+        },
+        _ => 0,
+    };
+    tokens.get(vis_len).or_else(|| tokens.first())
+}
+
+pub(crate) struct LitStr {
+    pub(crate) token: Literal,
+    value: String,
+}
+
+impl LitStr {
+    pub(crate) fn new(token: Literal) -> Result<Self> {
+        let repr = token.to_string();
+        // `r"..."` or `r#"..."#`
+        let hashes = if repr.starts_with('r') {
+            repr[1..].chars().take_while(|&c| c == '#').count()
+        } else {
+            0
+        };
+        let prefix = if repr.starts_with('r') { 1 + hashes } else { 0 };
+        // unlike `syn::LitStr`, escapes and suffixes are not supported
+        if repr.len() >= prefix + 2 + hashes
+            && repr[prefix..].starts_with('"')
+            && repr[..repr.len() - hashes].ends_with('"')
+        {
+            let value = repr[prefix + 1..repr.len() - hashes - 1].to_owned();
+            Ok(Self { token, value })
+        } else if repr.starts_with(|c: char| c.is_ascii_digit()) {
+            bail!(token.span(), "expected string literal, found `{}`; try `\"{}\"`", repr, repr)
+        } else {
+            bail!(token.span(), "expected string literal, found `{}`", repr)
+        }
+    }
+
+    pub(crate) fn value(&self) -> &str {
+        &self.value
+    }
+
+    pub(crate) fn span(&self) -> Span {
+        self.token.span()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signature(s: &str) -> String {
+        let input = &mut TokenIter::new(s.parse().unwrap());
+        parse_attrs(input).unwrap();
+        super::parse_signature(input).into_iter().collect::<TokenStream>().to_string()
+    }
+
+    #[test]
+    fn parse_signature() {
+        let cases: &[(&str, &str)] = &[
+            ("fn f() {}", "const fn"),
+            ("pub fn f() {}", "pub const fn"),
+            ("pub(crate) const fn f() {}", "pub (crate) const fn"),
+            ("#[inline] unsafe fn f() {}", "const unsafe fn"),
+            ("const unsafe fn f() {}", "const unsafe fn"),
+            ("async fn f() {}", "const async fn"),
+            ("extern \"C\" fn f() {}", "const extern \"C\" fn"),
+            ("pub unsafe extern \"C\" fn f() {}", "pub const unsafe extern \"C\" fn"),
+        ];
+        for &(input, expected) in cases {
+            assert_eq!(signature(input), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn parse_input() {
+        let func = super::parse_input("pub(crate) fn r#f() {}".parse().unwrap(), "a").unwrap();
+        assert_eq!(func.name().unwrap(), "f");
+        assert_eq!(func.vis().to_string(), "pub (crate)");
+        assert!(func.pub_token().is_none());
+        let func =
+            super::parse_input("#[cfg(a)] #[inline] pub fn f() {}".parse().unwrap(), "a").unwrap();
+        assert_eq!(func.cfg_attrs().to_string(), "# [cfg (a)]");
+        assert!(func.pub_token().is_some());
+
+        for input in &["struct S;", "fn", "#[a] struct S;"] {
+            assert!(super::parse_input(input.parse().unwrap(), "a").is_err(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn parse_static() {
+        let item =
+            super::parse_static("pub static X: Box<dyn Fn() -> u8> = make();".parse().unwrap())
+                .unwrap();
+        assert_eq!(item.vis.to_string(), "pub");
+        assert_eq!(item.name.to_string(), "X");
+        assert_eq!(item.ty.to_string(), "Box < dyn Fn () -> u8 >");
+        assert_eq!(item.expr.to_string(), "make ()");
+        let item = super::parse_static(
+            "static X: Option<Box<dyn Iterator<Item = u8>>> = None;".parse().unwrap(),
+        )
+        .unwrap();
+        assert_eq!(item.ty.to_string(), "Option < Box < dyn Iterator < Item = u8 >>>");
+
+        for input in &["static mut X: u8 = 0;", "static X: u8;", "static X = 0;", "fn f() {}"] {
+            assert!(super::parse_static(input.parse().unwrap()).is_err(), "{}", input);
+        }
+    }
+}
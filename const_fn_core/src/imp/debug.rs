@@ -0,0 +1,117 @@
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+// Tracing of `#[const_fn]` expansions, enabled by `CONST_FN_DEBUG` environment
+// variable.
+//
+// The value is a comma-separated list of function names, or `*` for all
+// functions. The build script reruns when it changes, so changing the value
+// re-expands the macros.
+
+use std::env;
+
+use super::pm::TokenStream;
+
+use super::{Arg, Cond, FORCE, Result, ast::Func, version};
+
+const FILTER: Option<&str> = option_env!("CONST_FN_DEBUG");
+
+pub(crate) struct Trace {
+    name: String,
+    arg: String,
+    decision: String,
+}
+
+impl Trace {
+    // Returns `None` if tracing is disabled for the function. The name of the
+    // function is only computed if `CONST_FN_DEBUG` is set.
+    pub(crate) fn new(func: &Func, arg: &Arg) -> Result<Option<Self>> {
+        let filter = match FILTER {
+            Some(filter) => filter,
+            None => return Ok(None),
+        };
+        let name = func.name()?;
+        if !is_enabled(filter, &name) {
+            return Ok(None);
+        }
+        Ok(Some(Self { name, arg: arg_to_string(arg), decision: String::new() }))
+    }
+
+    pub(crate) fn decision(&mut self, cond: &Cond) {
+        self.decision = match (cond, FORCE) {
+            (Cond::Bool(b), Some(_)) => {
+                format!("{} (forced by CONST_FN_FORCE)", if *b { "const" } else { "non-const" })
+            }
+            (Cond::Bool(true), None) => "const".to_owned(),
+            (Cond::Bool(false), None) => "non-const".to_owned(),
+            (Cond::Cfg(cfg), _) => format!("const if cfg({})", cfg),
+        };
+    }
+
+    // Prints the trace with the expanded tokens to stderr.
+    pub(crate) fn finish(self, tokens: &TokenStream) {
+        let version = match version() {
+            Ok(v) => {
+                format!("1.{}.{}{}", v.minor, v.patch, if v.is_nightly() { "-nightly" } else { "" })
+            }
+            Err(_) => "unknown".to_owned(),
+        };
+        let krate = env::var("CARGO_CRATE_NAME").unwrap_or_default();
+        eprint!("{}", format(&krate, &self, &version, &tokens.to_string()));
+    }
+}
+
+fn is_enabled(filter: &str, name: &str) -> bool {
+    filter.split(',').map(str::trim).any(|f| f == "*" || f == name)
+}
+
+fn arg_to_string(arg: &Arg) -> String {
+    match arg {
+        Arg::Version(req) => format!("\"{}.{}\"", req.major, req.minor),
+        Arg::Nightly => "nightly".to_owned(),
+        Arg::Cfg(cfg) => format!("cfg({})", cfg),
+        Arg::Feature(tokens) => tokens.to_string(),
+        Arg::Always => "(none)".to_owned(),
+    }
+}
+
+fn format(krate: &str, trace: &Trace, version: &str, tokens: &str) -> String {
+    format!(
+        "[const_fn] {}{}{}\n  arg: {}\n  rustc: {}\n  decision: {}\n  output: {}\n",
+        krate,
+        if krate.is_empty() { "" } else { "::" },
+        trace.name,
+        trace.arg,
+        version,
+        trace.decision,
+        tokens
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filter() {
+        assert!(is_enabled("*", "f"));
+        assert!(is_enabled("f", "f"));
+        assert!(is_enabled("a, f", "f"));
+        assert!(!is_enabled("a,b", "f"));
+        assert!(!is_enabled("", "f"));
+    }
+
+    #[test]
+    fn output() {
+        let mut trace = Trace {
+            name: "f".to_owned(),
+            arg: arg_to_string(&Arg::Cfg("feature = \"a\"".parse().unwrap())),
+            decision: String::new(),
+        };
+        trace.decision(&Cond::Cfg("feature = \"a\"".parse().unwrap()));
+        assert_eq!(
+            format("my_crate", &trace, "1.83.0", "fn f() {}"),
+            "[const_fn] my_crate::f\n  arg: cfg(feature = \"a\")\n  rustc: 1.83.0\n  \
+             decision: const if cfg(feature = \"a\")\n  output: fn f() {}\n"
+        );
+    }
+}
@@ -2,11 +2,13 @@
 
 use std::iter::FromIterator;
 
-use proc_macro::{Delimiter, Group, Ident, Literal, Punct, Spacing, Span, TokenStream, TokenTree};
+use super::pm::{Delimiter, Group, Ident, Literal, Punct, Spacing, Span, TokenStream, TokenTree};
 
+// `Error` must be in scope where these macros are used: this module is compiled
+// twice (see lib.rs), so the macros cannot refer to it by an absolute path.
 macro_rules! format_err {
     ($span:expr, $msg:expr $(,)*) => {
-        crate::error::Error::new($span, String::from($msg))
+        Error::new($span, String::from($msg))
     };
     ($span:expr, $($tt:tt)*) => {
         format_err!($span, format!($($tt)*))
@@ -21,6 +23,7 @@ macro_rules! bail {
 
 pub(crate) type Result<T, E = Error> = std::result::Result<T, E>;
 
+#[cfg_attr(test, derive(Debug))]
 pub(crate) struct Error {
     span: Span,
     msg: String,
@@ -31,6 +34,10 @@ impl Error {
         Self { span, msg }
     }
 
+    pub(crate) fn message(&self) -> &str {
+        &self.msg
+    }
+
     // https://github.com/dtolnay/syn/blob/1.0.39/src/error.rs#L218-L237
     pub(crate) fn into_compile_error(self) -> TokenStream {
         // compile_error!($msg)
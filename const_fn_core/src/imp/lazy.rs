@@ -0,0 +1,58 @@
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+use super::pm::{TokenStream, TokenTree};
+
+use super::{
+    Cond, Result, ast, eval_const, parse_arg,
+    to_tokens::ToTokens,
+    track_manifest,
+    utils::{cfg_attrs, interpolate},
+};
+
+// The wrapper is defined in `const_fn_core::__private`, so the user's crate
+// must depend on const_fn_core. Constructing it requires Rust 1.32, where
+// `Once::new` and `UnsafeCell::new` became const.
+//
+// The type of the value is only written in the type of the static, as elided
+// lifetimes there are `'static`.
+const LAZY: &str = "
+    __NAME: ::const_fn_core::__private::Lazy<__TY> = ::const_fn_core::__private::Lazy {
+        once: ::std::sync::Once::new(),
+        value: ::core::cell::UnsafeCell::new(::core::option::Option::None),
+        init: || __EXPR,
+    };
+";
+
+pub(crate) fn expand(args: TokenStream, input: TokenStream) -> Result<TokenStream> {
+    let arg = parse_arg(args)?;
+    let item = ast::parse_static(input)?;
+    let mut tokens = match eval_const(arg)? {
+        Cond::Cfg(cfg) => {
+            let (cfg, cfg_not) = cfg_attrs(cfg);
+            let mut tokens = cfg;
+            item.to_tokens(&mut tokens);
+            tokens.extend(lazy(&item, cfg_not));
+            tokens
+        }
+        Cond::Bool(true) => item.to_token_stream(),
+        Cond::Bool(false) => lazy(&item, TokenStream::new()),
+    };
+    let manifest = track_manifest(&format!("__CONST_FN_MANIFEST_{}", item.name));
+    if !manifest.is_empty() {
+        tokens.extend(item.cfg_attrs());
+        tokens.extend(manifest);
+    }
+    Ok(tokens)
+}
+
+fn lazy(item: &ast::Static, cfg: TokenStream) -> TokenStream {
+    let name: TokenStream = Some(TokenTree::Ident(item.name.clone())).into_iter().collect();
+
+    let mut tokens = cfg;
+    tokens.extend(item.head());
+    tokens.extend(interpolate(
+        LAZY.parse().unwrap(),
+        &[("__NAME", &name), ("__TY", &item.ty), ("__EXPR", &item.expr)],
+    ));
+    tokens
+}
@@ -0,0 +1,1061 @@
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+// The implementation of the macros. This module is compiled once with the token
+// types of `proc_macro` and once with those of `proc-macro2` (see lib.rs), and
+// refers to them as `pm`.
+
+#[macro_use]
+mod error;
+
+mod ast;
+mod debug;
+mod iter;
+mod lazy;
+mod to_tokens;
+mod utils;
+mod version_match;
+
+use super::pm;
+
+use std::{env, path::Path, str::FromStr};
+
+use self::{
+    ast::LitStr,
+    error::{Error, Result},
+    iter::TokenIter,
+    pm::{Delimiter, Group, Ident, Literal, Span, TokenStream, TokenTree},
+    to_tokens::ToTokens,
+    utils::{cfg_attrs, parse_as_empty, pretty_cfg, respan, tt_span},
+};
+use crate::{Condition, manifest};
+
+/// Expands `#[const_fn(<args>)]` applied to `input`.
+///
+/// See [`const_fn::const_fn`](https://docs.rs/const_fn/latest/const_fn/attr.const_fn.html) for details.
+pub fn const_fn(args: TokenStream, input: TokenStream) -> TokenStream {
+    expand(args, input).unwrap_or_else(Error::into_compile_error)
+}
+
+fn expand(args: TokenStream, input: TokenStream) -> Result<TokenStream> {
+    let args = parse_args(args)?;
+    let mut func = ast::parse_input(input, "const_fn")?;
+    check_strict(&args.arg, &func)?;
+    if args.doc {
+        if let Some(doc) = args.arg.doc() {
+            func.push_doc(&doc);
+        }
+    }
+    func.const_attrs = args.with.unwrap_or_default();
+    func.non_const_attrs = args.without.unwrap_or_default();
+    let mut trace = debug::Trace::new(&func, &args.arg)?;
+    let cond = eval_const(args.arg)?;
+    if let Some(trace) = &mut trace {
+        trace.decision(&cond);
+    }
+    // With `check_both`, a private copy of the inactive variant is compiled as
+    // well, so that both variants are type-checked: with a cfg condition, a
+    // const copy where the function is not const and a non-const copy where it
+    // is const. With a condition evaluated when the macro expands, only the
+    // non-const copy can be checked; if the condition is false, the body may
+    // not be valid in const contexts on the current compiler.
+    let mut check_copy = if args.check_both { Some(func.check_copy()?) } else { None };
+    // Read the manifest only if a non-const variant is generated.
+    let allow_lints = match cond {
+        Cond::Bool(true) if check_copy.is_none() => false,
+        _ => manifest::read().map_or(false, |metadata| metadata.allow_lints),
+    };
+    let manifest = track_manifest("__CONST_FN_MANIFEST");
+    if !manifest.is_empty() && func.block().is_ok() {
+        func.prepend_to_block(manifest);
+    }
+    let tokens = match cond {
+        Cond::Cfg(cfg) => {
+            // With `doc`, always render the const variant in the documentation.
+            let pred = if args.doc { or_doc(cfg.clone()) } else { cfg.clone() };
+            let mut tokens = const_fn_pair(&mut func, pred, allow_lints);
+            if let Some(copy) = &mut check_copy {
+                let (cfg, cfg_not) = cfg_attrs(cfg.clone());
+                for (cfg, print_const) in vec![(cfg, false), (cfg_not, true)] {
+                    tokens.extend(cfg);
+                    copy.print_const = print_const;
+                    tokens.extend(check_copy_tokens(copy, allow_lints));
+                }
+            }
+            if args.export_flag {
+                let (cfg, cfg_not) = cfg_attrs(cfg);
+                tokens.extend(cfg);
+                tokens.extend(export_flag(&func, true)?);
+                tokens.extend(cfg_not);
+                tokens.extend(export_flag(&func, false)?);
+            }
+            tokens
+        }
+        Cond::Bool(false) if args.doc && FORCE.is_none() => {
+            let mut tokens = const_fn_pair(&mut func, "doc".parse().unwrap(), allow_lints);
+            if args.export_flag {
+                tokens.extend(export_flag(&func, false)?);
+            }
+            tokens
+        }
+        Cond::Bool(b) => {
+            func.print_const = b;
+            let mut tokens = TokenStream::new();
+            if !b && allow_lints {
+                tokens.extend(NON_CONST_LINTS.parse::<TokenStream>().unwrap());
+            }
+            func.to_tokens(&mut tokens);
+            if let (true, Some(copy)) = (b, &mut check_copy) {
+                copy.print_const = false;
+                tokens.extend(check_copy_tokens(copy, allow_lints));
+            }
+            if args.export_flag {
+                tokens.extend(export_flag(&func, b)?);
+            }
+            tokens
+        }
+    };
+    if let Some(trace) = trace {
+        trace.finish(&tokens);
+    }
+    Ok(tokens)
+}
+
+// Lints allowed on the non-const variants with `package.metadata.const_fn.allow-lints`:
+// the non-const variant of the function and the non-const copy for `check_both`.
+// `#[expect]` is not used because clippy does not always flag the variant.
+//
+// Other lints do not need to be allowed: only one of the const and non-const
+// variants is compiled, so `dead_code` and the documentation lints fire once
+// for the function, and the copy has `#[allow(dead_code)]` and no
+// documentation, and is private, so clippy's documentation and `must_use`
+// lints, which only check public functions, do not apply to it.
+const NON_CONST_LINTS: &str = "#[allow(clippy::missing_const_for_fn)]";
+
+// `#[cfg(<pred>)] const fn ... #[cfg(not(<pred>))] fn ...`
+fn const_fn_pair(func: &mut ast::Func, pred: TokenStream, allow_lints: bool) -> TokenStream {
+    let (cfg, cfg_not) = cfg_attrs(pred);
+    let mut tokens = cfg;
+    func.print_const = true;
+    func.to_tokens(&mut tokens);
+    tokens.extend(cfg_not);
+    if allow_lints {
+        tokens.extend(NON_CONST_LINTS.parse::<TokenStream>().unwrap());
+    }
+    func.print_const = false;
+    func.to_tokens(&mut tokens);
+    tokens
+}
+
+// `#[doc(hidden)] #[allow(...)] [const] fn __const_fn_check_<name>(...) { ... }`
+fn check_copy_tokens(copy: &ast::Func, allow_lints: bool) -> TokenStream {
+    let mut tokens: TokenStream =
+        "#[doc(hidden)] #[allow(dead_code, non_snake_case)]".parse().unwrap();
+    if allow_lints && !copy.print_const {
+        tokens.extend(NON_CONST_LINTS.parse::<TokenStream>().unwrap());
+    }
+    copy.to_tokens(&mut tokens);
+    tokens
+}
+
+// `any(doc, <pred>)`
+fn or_doc(pred: TokenStream) -> TokenStream {
+    let mut list: TokenStream = "doc,".parse().unwrap();
+    list.extend(pred);
+    let mut tokens: TokenStream = "any".parse().unwrap();
+    tokens.extend(Some(TokenTree::Group(Group::new(Delimiter::Parenthesis, list))));
+    tokens
+}
+
+// In strict mode, rejects public functions whose constness is not controlled
+// by the user (i.e., depends on the compiler or the build environment).
+fn check_strict(arg: &Arg, func: &ast::Func) -> Result<()> {
+    let pub_token = match func.pub_token() {
+        Some(pub_token) => pub_token,
+        None => return Ok(()),
+    };
+    let reason = match arg {
+        Arg::Version(req) => format!("the compiler version (Rust {}.{})", req.major, req.minor),
+        Arg::Nightly => "whether the compiler is nightly".to_owned(),
+        Arg::Cfg(cfg) if !is_feature_only(cfg.clone()) => format!("`cfg({})`", cfg),
+        Arg::Cfg(_) | Arg::Feature(_) | Arg::Always => return Ok(()),
+    };
+    // Read the manifest only if needed; if it cannot be read, strict mode is
+    // considered disabled, as it is opt-in.
+    match manifest::read() {
+        Ok(ref metadata) if metadata.strict => {}
+        _ => return Ok(()),
+    }
+    bail!(
+        pub_token.span(),
+        "constness of this public function depends on {}; users of this crate may rely on \
+         the function being `const` and then fail to compile with a different compiler or \
+         build environment (this is denied by `package.metadata.const_fn.strict`); use a \
+         `feature` condition or make the function non-public",
+        reason
+    )
+}
+
+// Returns `true` if the given cfg predicate consists only of `feature = "..."`
+// combined by `all`, `any`, and `not`.
+fn is_feature_only(cfg: TokenStream) -> bool {
+    let iter = &mut TokenIter::new(cfg);
+    let mut is_empty = true;
+    while let Some(tt) = iter.next() {
+        is_empty = false;
+        match tt {
+            TokenTree::Ident(ref i) if i.to_string() == "feature" => {
+                match (iter.next(), iter.next()) {
+                    (Some(TokenTree::Punct(ref p)), Some(TokenTree::Literal(_)))
+                        if p.as_char() == '=' => {}
+                    _ => return false,
+                }
+            }
+            TokenTree::Ident(ref i)
+                if i.to_string() == "all" || i.to_string() == "any" || i.to_string() == "not" =>
+            {
+                match iter.next() {
+                    Some(TokenTree::Group(ref g)) if g.delimiter() == Delimiter::Parenthesis => {
+                        if !g.stream().is_empty() && !is_feature_only(g.stream()) {
+                            return false;
+                        }
+                    }
+                    _ => return false,
+                }
+            }
+            _ => return false,
+        }
+        match iter.next() {
+            Some(TokenTree::Punct(ref p)) if p.as_char() == ',' => {}
+            None => break,
+            Some(_) => return false,
+        }
+    }
+    !is_empty
+}
+
+// `#[doc(hidden)] <vis> const __CONST_FN_IS_CONST_<name>: bool = <is_const>;`
+fn export_flag(func: &ast::Func, is_const: bool) -> Result<TokenStream> {
+    let mut tokens = func.cfg_attrs();
+    tokens.extend(
+        "#[doc(hidden)] #[allow(dead_code, non_upper_case_globals)]"
+            .parse::<TokenStream>()
+            .unwrap(),
+    );
+    tokens.extend(func.vis());
+    tokens.extend(
+        format!("const __CONST_FN_IS_CONST_{}: bool = {};", func.name()?, is_const)
+            .parse::<TokenStream>()
+            .unwrap(),
+    );
+    Ok(tokens)
+}
+
+/// Expands `#[cfg_rustc(<args>)]` applied to `input`.
+///
+/// See [`const_fn::cfg_rustc`](https://docs.rs/const_fn/latest/const_fn/attr.cfg_rustc.html) for details.
+pub fn cfg_rustc(args: TokenStream, input: TokenStream) -> TokenStream {
+    expand_cfg_rustc(args, input).unwrap_or_else(Error::into_compile_error)
+}
+
+fn expand_cfg_rustc(args: TokenStream, input: TokenStream) -> Result<TokenStream> {
+    Ok(match parse_arg(args)?.eval()? {
+        Cond::Cfg(cfg) => {
+            let (mut tokens, _) = cfg_attrs(cfg);
+            tokens.extend(input);
+            tokens
+        }
+        Cond::Bool(true) => input,
+        Cond::Bool(false) => TokenStream::new(),
+    })
+}
+
+/// Expands `#[const_test(<args>)]` applied to `input`.
+///
+/// See [`const_fn::const_test`](https://docs.rs/const_fn/latest/const_fn/attr.const_test.html) for details.
+pub fn const_test(args: TokenStream, input: TokenStream) -> TokenStream {
+    expand_const_test(args, input).unwrap_or_else(Error::into_compile_error)
+}
+
+fn expand_const_test(args: TokenStream, input: TokenStream) -> Result<TokenStream> {
+    let arg = parse_arg(args)?;
+    let mut func = ast::parse_input(input, "const_test")?;
+    let block = func.block()?;
+    func.print_const = false;
+
+    let cond = eval_const(arg)?;
+    let manifest = track_manifest("__CONST_FN_MANIFEST");
+    if !manifest.is_empty() {
+        func.prepend_to_block(manifest);
+    }
+    let mut tokens = func.to_token_stream();
+    let cfg = match cond {
+        Cond::Cfg(cfg) => cfg_attrs(cfg).0,
+        Cond::Bool(true) => TokenStream::new(),
+        Cond::Bool(false) => return Ok(tokens),
+    };
+    // `#[cfg(test)] #[cfg(...)] const __CONST_FN_TEST_<name>: () = { ... };`
+    //
+    // The const item is named after the test instead of `const _`, which
+    // requires Rust 1.37.
+    tokens.extend("#[cfg(test)]".parse::<TokenStream>().unwrap());
+    tokens.extend(func.cfg_attrs());
+    tokens.extend(cfg);
+    tokens.extend(
+        format!(
+            "#[allow(dead_code, non_upper_case_globals)] const __CONST_FN_TEST_{}: () =",
+            func.name()?
+        )
+        .parse::<TokenStream>()
+        .unwrap(),
+    );
+    tokens.extend(Some(block));
+    tokens.extend(";".parse::<TokenStream>().unwrap());
+    Ok(tokens)
+}
+
+/// Expands `#[const_or_lazy(<args>)]` applied to `input`.
+///
+/// See [`const_fn::const_or_lazy`](https://docs.rs/const_fn/latest/const_fn/attr.const_or_lazy.html) for details.
+pub fn const_or_lazy(args: TokenStream, input: TokenStream) -> TokenStream {
+    lazy::expand(args, input).unwrap_or_else(Error::into_compile_error)
+}
+
+/// Parses the arguments of `#[const_fn(<args>)]` without expanding anything.
+///
+/// This is intended for tools that inspect the conditions written in the
+/// source code. Options such as `export_flag` are accepted and ignored.
+/// Aliases are resolved using Cargo.toml in `manifest_dir`, which is usually
+/// the directory of the package containing the attribute.
+///
+/// Unlike the other functions in this crate, this does not evaluate the
+/// condition, and `CONST_FN_FORCE` is not taken into account.
+pub fn parse_condition(args: TokenStream, manifest_dir: &Path) -> Result<Condition, String> {
+    let args = manifest::with_manifest_dir(manifest_dir, || parse_args(args));
+    // Nothing is expanded, so Cargo.toml does not need to be tracked.
+    let _ = manifest::take_read();
+    let args = args.map_err(|e| e.message().to_owned())?;
+    Ok(match args.arg {
+        Arg::Always => Condition::Always,
+        Arg::Version(req) => Condition::Version { major: req.major, minor: req.minor },
+        Arg::Nightly => Condition::Nightly,
+        Arg::Feature(tokens) => match tokens.into_iter().nth(2) {
+            Some(TokenTree::Literal(l)) => Condition::Feature(
+                LitStr::new(l).map_err(|e| e.message().to_owned())?.value().to_owned(),
+            ),
+            _ => unreachable!(),
+        },
+        Arg::Cfg(cfg) => Condition::Cfg(pretty_cfg(cfg)),
+    })
+}
+
+enum Arg {
+    // `#[const_fn("...")]`
+    Version(VersionReq),
+    // `#[const_fn(nightly)]`
+    Nightly,
+    // `#[const_fn(cfg(...))]`
+    Cfg(TokenStream),
+    // `#[const_fn(feature = "...")]`
+    Feature(TokenStream),
+    // Note: `#[const_fn(alias = "...")]` is resolved to one of the other
+    // variants while parsing.
+    // `#[const_fn]`
+    Always,
+}
+
+/// Expands `version_match!(<input>)`.
+///
+/// See [`const_fn::version_match`](https://docs.rs/const_fn/latest/const_fn/macro.version_match.html) for details.
+pub fn version_match(input: TokenStream) -> TokenStream {
+    version_match::expand(input).unwrap_or_else(Error::into_compile_error)
+}
+
+/// Expands `rustc_version!(<input>)`.
+///
+/// See [`const_fn::rustc_version`](https://docs.rs/const_fn/latest/const_fn/macro.rustc_version.html) for details.
+pub fn rustc_version(input: TokenStream) -> TokenStream {
+    expand_rustc_version(input).unwrap_or_else(Error::into_compile_error)
+}
+
+fn expand_rustc_version(input: TokenStream) -> Result<TokenStream> {
+    parse_as_empty(&mut TokenIter::new(input))?;
+    let version = version()?;
+    // Use only literals so that the expansion is valid in any edition and
+    // does not depend on the paths available in the calling crate.
+    Ok(format!("(1u32, {}u32, {}u32, {})", version.minor, version.patch, version.is_nightly())
+        .parse()
+        .unwrap())
+}
+
+/// Expands `assert_const!(<input>)`.
+///
+/// See [`const_fn::assert_const`](https://docs.rs/const_fn/latest/const_fn/macro.assert_const.html) for details.
+pub fn assert_const(input: TokenStream) -> TokenStream {
+    expand_assert_const(input).unwrap_or_else(Error::into_compile_error)
+}
+
+fn expand_assert_const(input: TokenStream) -> Result<TokenStream> {
+    let iter = &mut TokenIter::new(input);
+    let arg = parse_cond(iter)?;
+    match iter.next() {
+        Some(TokenTree::Punct(ref p)) if p.as_char() == ',' => {}
+        tt => bail!(tt_span(tt.as_ref()), "expected `,`"),
+    }
+    let mut expr: Vec<_> = iter.collect();
+    match expr.last() {
+        // allow trailing comma
+        Some(TokenTree::Punct(p)) if p.as_char() == ',' => {
+            expr.pop();
+        }
+        _ => {}
+    }
+    if expr.is_empty() {
+        bail!(Span::call_site(), "expected expression");
+    }
+    let expr: TokenStream = expr.into_iter().collect();
+
+    // `{ const __CONST_FN_ASSERT: () = (ManuallyDrop::new(<expr>), ()).1; }`
+    //
+    // The const item is named and put in a block instead of `const _`, which
+    // requires Rust 1.37, and the value is discarded by a tuple field instead of
+    // `let`, which requires Rust 1.33. `ManuallyDrop` avoids running the
+    // destructor in const context, but `ManuallyDrop::new` is const only since
+    // Rust 1.32, so on Rust 1.31 the value must not have a destructor.
+    let manually_drop = version()?.minor >= 32;
+    let const_item = |cfg: TokenStream| -> TokenStream {
+        let mut tokens = cfg;
+        tokens.extend(
+            "#[allow(dead_code, non_upper_case_globals)] const __CONST_FN_ASSERT: () ="
+                .parse::<TokenStream>()
+                .unwrap(),
+        );
+        let mut tuple = if manually_drop {
+            "::core::mem::ManuallyDrop::new".parse().unwrap()
+        } else {
+            TokenStream::new()
+        };
+        tuple.extend(Some(TokenTree::Group(Group::new(Delimiter::Parenthesis, expr.clone()))));
+        tuple.extend(", ()".parse::<TokenStream>().unwrap());
+        tokens.extend(Some(TokenTree::Group(Group::new(Delimiter::Parenthesis, tuple))));
+        tokens.extend(".1;".parse::<TokenStream>().unwrap());
+        tokens
+    };
+    let runtime = |cfg: TokenStream| -> TokenStream {
+        let mut tokens = cfg;
+        tokens.extend("let _ = ".parse::<TokenStream>().unwrap());
+        tokens.extend(expr.clone());
+        tokens.extend(";".parse::<TokenStream>().unwrap());
+        tokens
+    };
+    let cond = eval_const(arg)?;
+    let mut tokens = track_manifest("__CONST_FN_MANIFEST");
+    match cond {
+        Cond::Cfg(cfg) => {
+            let (cfg, cfg_not) = cfg_attrs(cfg);
+            tokens.extend(const_item(cfg));
+            tokens.extend(runtime(cfg_not));
+        }
+        Cond::Bool(true) => tokens.extend(const_item(TokenStream::new())),
+        Cond::Bool(false) => tokens.extend(runtime(TokenStream::new())),
+    }
+    Ok(TokenStream::from(TokenTree::Group(Group::new(Delimiter::Brace, tokens))))
+}
+
+// `const <name>: &[u8] = include_bytes!("<path>");` if Cargo.toml has been
+// read by the current expansion (e.g., to resolve an alias), so that Cargo.toml
+// is an input of the crate and changing it expands the macros again.
+fn track_manifest(name: &str) -> TokenStream {
+    let path = match manifest::take_read() {
+        Some(path) => path,
+        None => return TokenStream::new(),
+    };
+    let path = match path.to_str() {
+        Some(path) => Literal::string(path),
+        None => return TokenStream::new(),
+    };
+    let mut tokens: TokenStream = format!(
+        "#[allow(dead_code, non_upper_case_globals)] const {}: &[u8] = include_bytes!",
+        name
+    )
+    .parse()
+    .unwrap();
+    tokens.extend(Some(TokenTree::Group(Group::new(
+        Delimiter::Parenthesis,
+        TokenStream::from(TokenTree::Literal(path)),
+    ))));
+    tokens.extend(";".parse::<TokenStream>().unwrap());
+    tokens
+}
+
+// Evaluates the condition of constness, taking `CONST_FN_FORCE` into account.
+fn eval_const(arg: Arg) -> Result<Cond> {
+    match FORCE {
+        Some(force) => Ok(Cond::Bool(force)),
+        None => arg.eval(),
+    }
+}
+
+// The result of evaluating `Arg`.
+enum Cond {
+    // The condition has been evaluated when the macro expands.
+    Bool(bool),
+    // The condition is evaluated by the compiler: `#[cfg(...)]`
+    Cfg(TokenStream),
+}
+
+impl Arg {
+    // The paragraph added to the documentation of the function.
+    fn doc(&self) -> Option<String> {
+        let cond = match self {
+            Arg::Version(req) => format!("on Rust {}.{} and later", req.major, req.minor),
+            Arg::Nightly => "on the nightly compiler".to_owned(),
+            Arg::Feature(tokens) => match tokens.clone().into_iter().nth(2) {
+                Some(TokenTree::Literal(l)) => match LitStr::new(l) {
+                    Ok(l) => format!("when the `{}` feature is enabled", l.value()),
+                    Err(_) => return None,
+                },
+                _ => return None,
+            },
+            Arg::Cfg(cfg) => format!("when `cfg({})` is true", pretty_cfg(cfg.clone())),
+            Arg::Always => return None,
+        };
+        Some(format!("This function is `const` {}.", cond))
+    }
+
+    fn eval(self) -> Result<Cond> {
+        Ok(match self {
+            Arg::Version(req) => {
+                let version = version()?;
+                Cond::Bool(
+                    !(req.major > 1
+                        || req.minor + cfg!(const_fn_assume_incomplete_release) as u32
+                            > version.minor),
+                )
+            }
+            Arg::Nightly => Cond::Bool(version()?.is_nightly()),
+            Arg::Cfg(cfg) | Arg::Feature(cfg) => Cond::Cfg(cfg),
+            Arg::Always => Cond::Bool(true),
+        })
+    }
+}
+
+// `#[const_fn(<arg>, <option>,*)]`
+struct Args {
+    arg: Arg,
+    // `export_flag`
+    export_flag: bool,
+    // `check_both`
+    check_both: bool,
+    // `doc`
+    doc: bool,
+    // `with(...)`
+    with: Option<Vec<ast::Attribute>>,
+    // `without(...)`
+    without: Option<Vec<ast::Attribute>>,
+}
+
+fn parse_args(tokens: TokenStream) -> Result<Args> {
+    let iter = &mut TokenIter::new(tokens);
+
+    let arg = parse_cond(iter)?;
+    let mut args =
+        Args { arg, export_flag: false, check_both: false, doc: false, with: None, without: None };
+    while let Some(tt) = iter.next() {
+        match tt {
+            TokenTree::Punct(ref p) if p.as_char() == ',' => {}
+            tt => bail!(tt.span(), "expected `,`"),
+        }
+        let option = match iter.next() {
+            Some(TokenTree::Ident(i)) => i,
+            // allow trailing comma
+            None => break,
+            Some(tt) => bail!(tt.span(), "expected option"),
+        };
+        let name = option.to_string();
+        let flag = match &*name {
+            "export_flag" => &mut args.export_flag,
+            "check_both" => &mut args.check_both,
+            "doc" => &mut args.doc,
+            "with" | "without" => {
+                let attrs = match iter.next() {
+                    Some(TokenTree::Group(ref g)) if g.delimiter() == Delimiter::Parenthesis => {
+                        ast::parse_attr_list(g.stream())?
+                    }
+                    Some(tt) => bail!(tt.span(), "expected `(`"),
+                    None => bail!(option.span(), "expected `(` after `{}`", option),
+                };
+                let list = if name == "with" { &mut args.with } else { &mut args.without };
+                if list.is_some() {
+                    bail!(option.span(), "duplicate `{}` option", option);
+                }
+                *list = Some(attrs);
+                continue;
+            }
+            _ => bail!(
+                option.span(),
+                "unknown option `{}`; expected one of: `export_flag`, `check_both`, `doc`, \
+                 `with`, `without`",
+                option
+            ),
+        };
+        if *flag {
+            bail!(option.span(), "duplicate `{}` option", option);
+        }
+        *flag = true;
+    }
+    Ok(args)
+}
+
+fn parse_arg(tokens: TokenStream) -> Result<Arg> {
+    let iter = &mut TokenIter::new(tokens);
+    let arg = parse_cond(iter)?;
+    parse_as_empty(iter)?;
+    Ok(arg)
+}
+
+fn parse_cond(iter: &mut TokenIter) -> Result<Arg> {
+    let next = iter.next();
+    let next_span = tt_span(next.as_ref());
+    match next {
+        None => return Ok(Arg::Always),
+        Some(TokenTree::Ident(i)) => match &*i.to_string() {
+            "nightly" => return Ok(Arg::Nightly),
+            "cfg" => {
+                return match iter.next().as_ref() {
+                    Some(TokenTree::Group(g)) if g.delimiter() == Delimiter::Parenthesis => {
+                        Ok(Arg::Cfg(g.stream()))
+                    }
+                    Some(tt) => bail!(tt.span(), "expected `(`"),
+                    None => bail!(i.span(), "expected `(` after `cfg`"),
+                };
+            }
+            "feature" => {
+                let (eq, l) = parse_eq_lit(iter, &i)?;
+                return Ok(Arg::Feature(
+                    vec![TokenTree::Ident(i), eq, l.token.into()].into_iter().collect(),
+                ));
+            }
+            "alias" => {
+                let (_, l) = parse_eq_lit(iter, &i)?;
+                return resolve_alias(&l);
+            }
+            _ => {}
+        },
+        Some(TokenTree::Literal(l)) => {
+            // `1.61` instead of `"1.61"`
+            let is_number = l.to_string().starts_with(|c: char| c.is_ascii_digit());
+            match LitStr::new(l) {
+                Ok(l) => {
+                    return match l.value().parse::<VersionReq>() {
+                        Ok(req) => Ok(Arg::Version(req)),
+                        Err(e) => bail!(l.span(), "{}", e),
+                    };
+                }
+                Err(e) if is_number => return Err(e),
+                Err(_) => {}
+            }
+        }
+        Some(_) => {}
+    }
+
+    bail!(next_span, "expected one of: `nightly`, `cfg`, `feature`, `alias`, string literal")
+}
+
+// `= "..."`
+fn parse_eq_lit(iter: &mut TokenIter, key: &Ident) -> Result<(TokenTree, LitStr)> {
+    let next = iter.next();
+    match next {
+        Some(TokenTree::Punct(ref p)) if p.as_char() == '=' => match iter.next() {
+            Some(TokenTree::Literal(l)) => Ok((next.unwrap(), LitStr::new(l)?)),
+            tt => bail!(tt_span(tt.as_ref()), "expected string literal"),
+        },
+        Some(tt) => bail!(tt.span(), "expected `=`"),
+        None => bail!(key.span(), "expected `= \"...\"` after `{}`", key),
+    }
+}
+
+// Looks up `[package.metadata.const_fn.aliases]` table in Cargo.toml of the
+// crate being compiled, and parses the value as if it was written inline.
+fn resolve_alias(name: &LitStr) -> Result<Arg> {
+    let metadata = manifest::read().map_err(|e| format_err!(name.span(), "{}", e))?;
+    let value = match metadata.alias(name.value()) {
+        Some(value) => value,
+        None => bail!(
+            name.span(),
+            "unknown alias `{}`; aliases must be defined in `[package.metadata.const_fn.aliases]` table in Cargo.toml",
+            name.value()
+        ),
+    };
+    let tokens = match TokenStream::from_str(value) {
+        Ok(tokens) => respan(tokens, name.span()),
+        Err(_) => bail!(name.span(), "failed to parse alias `{}`: `{}`", name.value(), value),
+    };
+    match tokens.clone().into_iter().next() {
+        Some(TokenTree::Ident(ref i)) if i.to_string() == "alias" => {
+            bail!(name.span(), "alias `{}` cannot refer to another alias", name.value())
+        }
+        _ => parse_arg(tokens),
+    }
+}
+
+struct VersionReq {
+    major: u32,
+    minor: u32,
+}
+
+impl FromStr for VersionReq {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut pieces = s.split('.');
+        let major = pieces
+            .next()
+            .ok_or("need to specify the major version")?
+            .parse::<u32>()
+            .map_err(|e| e.to_string())?;
+        let minor = pieces
+            .next()
+            .ok_or("need to specify the minor version")?
+            .parse::<u32>()
+            .map_err(|e| e.to_string())?;
+        if let Some(s) = pieces.next() {
+            Err(format!("unexpected input: .{}", s))
+        } else {
+            Ok(Self { major, minor })
+        }
+    }
+}
+
+struct Version {
+    minor: u32,
+    patch: u32,
+    nightly: bool,
+    // Crates listed in `RUSTC_BOOTSTRAP=crate_a,crate_b`. They are compiled as
+    // if by a nightly compiler.
+    bootstrap_crates: &'static [&'static str],
+}
+
+impl Version {
+    fn is_nightly(&self) -> bool {
+        if self.nightly {
+            return true;
+        }
+        if self.bootstrap_crates.is_empty() {
+            return false;
+        }
+        // The crate currently being compiled.
+        let crate_name = match env::var("CARGO_CRATE_NAME") {
+            Ok(name) => name,
+            Err(_) => match env::var("CARGO_PKG_NAME") {
+                Ok(name) => name.replace('-', "_"),
+                Err(_) => return false,
+            },
+        };
+        self.bootstrap_crates.iter().any(|&name| name == crate_name)
+    }
+}
+
+// Use \ on Windows host to work around https://github.com/rust-lang/rust/issues/75075 / https://github.com/rust-lang/cargo/issues/13919.
+// (Fixed in Rust 1.84: https://github.com/rust-lang/rust/pull/125205)
+#[cfg(const_fn_has_build_script)]
+#[cfg(not(host_os = "windows"))]
+const VERSION: Version = include!(concat!(env!("OUT_DIR"), "/version"));
+#[cfg(const_fn_has_build_script)]
+#[cfg(host_os = "windows")]
+const VERSION: Version = include!(concat!(env!("OUT_DIR"), "\\version"));
+// If build script has not run (e.g., when building with a build system other than
+// Cargo) or unable to determine version, read the version from `CONST_FN_RUSTC_VERSION`
+// environment variable. If it is not set either, it is considered as our MSRV (Rust 1.31),
+// and a warning is printed.
+#[cfg(not(const_fn_has_build_script))]
+fn version() -> Result<Version> {
+    match env::var("CONST_FN_RUSTC_VERSION") {
+        Ok(v) => match Version::parse(&v) {
+            Some(version) => Ok(version),
+            None => bail!(
+                Span::call_site(),
+                "invalid value of CONST_FN_RUSTC_VERSION: {:?} (expected rustc version like `1.83.0` or `1.83.0-nightly`)",
+                v
+            ),
+        },
+        Err(_) => {
+            // Printed to stderr instead of emitting a lint, which can be
+            // silenced by `--cap-lints` (e.g., when the crate is a dependency)
+            // or `#[allow]`. This is printed on each expansion that needs the
+            // version, so that it is not missed in any crate.
+            eprintln!(
+                "warning: const_fn: unable to determine rustc version; assuming Rust 1.31 \
+                 (stable), so functions with version or nightly conditions are not `const`; \
+                 set CONST_FN_RUSTC_VERSION environment variable (e.g., \
+                 CONST_FN_RUSTC_VERSION=1.83.0) if the build script of const_fn_core does \
+                 not run in your build system"
+            );
+            Ok(Version { minor: 31, patch: 0, nightly: false, bootstrap_crates: &[] })
+        }
+    }
+}
+#[cfg(const_fn_has_build_script)]
+#[allow(clippy::unnecessary_wraps)]
+fn version() -> Result<Version> {
+    Ok(VERSION)
+}
+
+#[cfg(not(const_fn_has_build_script))]
+impl Version {
+    // Parses `<major>.<minor>[.<patch>][-<channel>]`.
+    fn parse(s: &str) -> Option<Self> {
+        let mut release = s.trim().splitn(2, '-');
+        let version = release.next().unwrap();
+        let channel = release.next().unwrap_or_default();
+        let mut digits = version.splitn(3, '.');
+        if digits.next()? != "1" {
+            return None;
+        }
+        let minor = digits.next()?.parse::<u32>().ok()?;
+        let patch = digits.next().unwrap_or("0").parse::<u32>().ok()?;
+        let nightly = channel == "nightly" || channel == "dev";
+        Some(Self { minor, patch, nightly, bootstrap_crates: &[] })
+    }
+}
+
+// Set by `CONST_FN_FORCE` environment variable or `--cfg const_fn_force="..."`.
+// `Some(true)` makes all functions `const`, `Some(false)` makes all functions non-`const`.
+#[cfg(all(const_fn_force = "always", not(const_fn_force = "never")))]
+const FORCE: Option<bool> = Some(true);
+#[cfg(const_fn_force = "never")]
+const FORCE: Option<bool> = Some(false);
+#[cfg(not(any(const_fn_force = "always", const_fn_force = "never")))]
+const FORCE: Option<bool> = None;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(s: &str) -> TokenStream {
+        s.parse().unwrap()
+    }
+
+    fn error(e: Error) -> String {
+        e.into_compile_error().to_string()
+    }
+
+    #[test]
+    fn version_req() {
+        let req = "1.61".parse::<VersionReq>().unwrap();
+        assert_eq!((req.major, req.minor), (1, 61));
+        assert_eq!("1".parse::<VersionReq>().err().unwrap(), "need to specify the minor version");
+        assert_eq!("1.61.0".parse::<VersionReq>().err().unwrap(), "unexpected input: .0");
+        assert!("".parse::<VersionReq>().is_err());
+        assert!("1.x".parse::<VersionReq>().is_err());
+        assert!("1.-1".parse::<VersionReq>().is_err());
+    }
+
+    #[test]
+    fn parse_arg_ok() {
+        let cases: &[(&str, &str)] = &[
+            ("", "always"),
+            ("\"1.61\"", "version 1.61"),
+            ("r\"1.61\"", "version 1.61"),
+            ("r#\"1.61\"#", "version 1.61"),
+            ("nightly", "nightly"),
+            ("cfg(all(a, not(b)))", "cfg all (a , not (b))"),
+            ("feature = \"x\"", "feature feature = \"x\""),
+        ];
+        for &(arg, expected) in cases {
+            let actual = match parse_arg(tokens(arg)).unwrap_or_else(|e| panic!("{}", error(e))) {
+                Arg::Version(req) => format!("version {}.{}", req.major, req.minor),
+                Arg::Nightly => "nightly".to_owned(),
+                Arg::Cfg(cfg) => format!("cfg {}", cfg),
+                Arg::Feature(f) => format!("feature {}", f),
+                Arg::Always => "always".to_owned(),
+            };
+            assert_eq!(actual, expected, "{}", arg);
+        }
+    }
+
+    #[test]
+    fn parse_arg_err() {
+        let cases: &[(&str, &str)] = &[
+            ("foo", "expected one of"),
+            ("\"1.x\"", "invalid digit"),
+            ("nightly, x", "unexpected token"),
+            ("cfg[a]", "expected `(`"),
+            ("feature", "after `feature`"),
+            ("cfg", "after `cfg`"),
+            ("feature = x", "expected string literal"),
+            ("1.61", "found `1.61`; try"),
+            ("'a'", "expected one of"),
+            ("feature = b\"x\"", "expected string literal, found"),
+        ];
+        for &(arg, expected) in cases {
+            let e = match parse_arg(tokens(arg)) {
+                Ok(_) => panic!("expected error: {}", arg),
+                Err(e) => error(e),
+            };
+            assert!(e.contains(expected), "{}: {}", arg, e);
+        }
+    }
+
+    #[test]
+    fn parse_args_options() {
+        let args = parse_args(tokens("\"1.61\", export_flag, check_both,")).unwrap();
+        assert!(args.export_flag && args.check_both);
+        let args = parse_args(tokens("nightly")).unwrap();
+        assert!(!args.export_flag && !args.check_both);
+        assert!(args.with.is_none() && args.without.is_none());
+        let args =
+            parse_args(tokens("nightly, with(#[must_use], #[inline],), without(#[inline(never)])"))
+                .unwrap();
+        assert_eq!(args.with.unwrap().len(), 2);
+        assert_eq!(args.without.unwrap().len(), 1);
+        for &(arg, expected) in &[
+            ("nightly, export_flag, export_flag", "duplicate `export_flag` option"),
+            ("nightly, foo", "unknown option `foo`"),
+            ("nightly export_flag", "expected `,`"),
+            ("nightly, with", "expected `(` after `with`"),
+            ("nightly, with = 1", "expected `(`"),
+            ("nightly, with(inline)", "expected attribute like `#[...]`"),
+            ("nightly, with(#(inline))", "expected `[`"),
+            ("nightly, without(#[a] #[b])", "expected `,`"),
+            ("nightly, with(), with()", "duplicate `with` option"),
+        ] {
+            let e = match parse_args(tokens(arg)) {
+                Ok(_) => panic!("expected error: {}", arg),
+                Err(e) => error(e),
+            };
+            assert!(e.contains(expected), "{}: {}", arg, e);
+        }
+    }
+
+    #[test]
+    fn parse_condition() {
+        let parse = |s| super::parse_condition(tokens(s), Path::new("."));
+        assert_eq!(parse("").unwrap(), Condition::Always);
+        assert_eq!(
+            parse("\"1.61\", export_flag").unwrap(),
+            Condition::Version { major: 1, minor: 61 }
+        );
+        assert_eq!(parse("nightly").unwrap(), Condition::Nightly);
+        assert_eq!(parse("feature = \"x\"").unwrap(), Condition::Feature("x".to_owned()));
+        assert_eq!(parse("cfg(a)").unwrap(), Condition::Cfg("a".to_owned()));
+        assert_eq!(parse("cfg(all(a,b))").unwrap(), Condition::Cfg("all(a, b)".to_owned()));
+        assert!(parse("foo").unwrap_err().starts_with("expected one of"));
+        // aliases are read from the given directory
+        let e = parse("alias = \"x\"").unwrap_err();
+        assert!(e.starts_with("unknown alias `x`"), "{}", e);
+    }
+
+    #[test]
+    fn doc() {
+        let expand = |args: &str, input: &str| super::const_fn(tokens(args), tokens(input));
+        let doc = |s: &str| tokens(&format!("#[doc = {:?}]", s)).to_string();
+        let paragraph =
+            doc("This function is `const` when `cfg(all(a, not(feature = \"b\")))` is true.");
+
+        let out =
+            expand("cfg(all(a, not(feature = \"b\"))), doc", "/// Docs.\nfn f() {}").to_string();
+        assert!(out.contains(&paragraph), "{}", out);
+        assert!(
+            out.contains(&tokens("#[cfg(any(doc, all(a, not(feature = \"b\"))))]").to_string()),
+            "{}",
+            out
+        );
+        // Without `doc`, the documentation is unchanged.
+        let out = expand("cfg(all(a, not(feature = \"b\")))", "/// Docs.\nfn f() {}").to_string();
+        assert!(!out.contains(&paragraph), "{}", out);
+        assert!(!out.contains("any (doc"), "{}", out);
+        // Undocumented functions are not documented.
+        let out = expand("cfg(all(a, not(feature = \"b\"))), doc", "#[doc(hidden)] fn f() {}")
+            .to_string();
+        assert!(!out.contains(&paragraph), "{}", out);
+
+        let out = expand("feature = \"x\", doc", "#[doc = \"Docs.\"] fn f() {}").to_string();
+        assert!(
+            out.contains(&doc("This function is `const` when the `x` feature is enabled.")),
+            "{}",
+            out
+        );
+    }
+
+    #[test]
+    fn assert_const() {
+        // `const _` requires Rust 1.37, and `let` in const context requires Rust 1.33.
+        let contains = |out: &str, s: &str| out.contains(&tokens(s).to_string());
+        let out = super::assert_const(tokens("cfg(a), f()")).to_string();
+        assert!(out.starts_with('{'), "{}", out);
+        assert!(
+            contains(
+                &out,
+                "const __CONST_FN_ASSERT: () = (::core::mem::ManuallyDrop::new(f()), ()).1;"
+            ),
+            "{}",
+            out
+        );
+        assert!(contains(&out, "#[cfg(not(a))] let _ = f();"), "{}", out);
+        assert!(!contains(&out, "const _:"), "{}", out);
+    }
+
+    #[test]
+    fn const_test() {
+        // `const _` requires Rust 1.37.
+        let contains = |out: &str, s: &str| out.contains(&tokens(s).to_string());
+        let out = super::const_test(tokens("cfg(a)"), tokens("#[test] fn t() {}")).to_string();
+        assert!(contains(&out, "const __CONST_FN_TEST_t: () = {};"), "{}", out);
+        assert!(!contains(&out, "const _:"), "{}", out);
+    }
+
+    #[test]
+    fn track_manifest() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../test_suite");
+        let path = Literal::string(dir.join("Cargo.toml").to_str().unwrap()).to_string();
+        let expand = |args: &str, input: &str| {
+            manifest::with_manifest_dir(&dir, || super::const_fn(tokens(args), tokens(input)))
+                .to_string()
+        };
+        // Cargo.toml is included only if the expansion has read it.
+        let out = expand("alias = \"min_const_fn\"", "fn f() {}");
+        assert!(out.contains(&format!("include_bytes ! ({})", path)), "{}", out);
+        let out = expand("\"1.31\"", "fn f() {}");
+        assert!(!out.contains("include_bytes"), "{}", out);
+        let out = manifest::with_manifest_dir(&dir, || {
+            super::const_or_lazy(tokens("alias = \"always_false\""), tokens("static X: u8 = 1;"))
+        })
+        .to_string();
+        assert!(out.contains("const __CONST_FN_MANIFEST_X : & [u8] = include_bytes !"), "{}", out);
+    }
+
+    #[test]
+    fn pretty_cfg() {
+        for &(input, expected) in &[
+            ("a", "a"),
+            ("feature = \"x\"", "feature = \"x\""),
+            ("all(a, not(b))", "all(a, not(b))"),
+            ("any(a,)", "any(a)"),
+            ("any()", "any()"),
+            (
+                "all(target_os = \"linux\", any(unix, windows))",
+                "all(target_os = \"linux\", any(unix, windows))",
+            ),
+        ] {
+            assert_eq!(utils::pretty_cfg(tokens(input)), expected);
+        }
+    }
+
+    #[test]
+    fn cfg_attrs() {
+        let (cfg, cfg_not) = utils::cfg_attrs(tokens("feature = \"x\""));
+        assert_eq!(cfg.to_string(), tokens("#[cfg(feature = \"x\")]").to_string());
+        assert_eq!(cfg_not.to_string(), tokens("#[cfg(not(feature = \"x\"))]").to_string());
+    }
+
+    #[test]
+    fn is_feature_only() {
+        assert!(super::is_feature_only(tokens("feature = \"x\"")));
+        assert!(super::is_feature_only(tokens("all(feature = \"x\", not(feature = \"y\"))")));
+        assert!(super::is_feature_only(tokens("any()")));
+        assert!(!super::is_feature_only(tokens("")));
+        assert!(!super::is_feature_only(tokens("miri")));
+        assert!(!super::is_feature_only(tokens("all(feature = \"x\", miri)")));
+        assert!(!super::is_feature_only(tokens("feature")));
+    }
+}
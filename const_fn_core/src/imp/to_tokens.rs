@@ -2,7 +2,7 @@
 
 use std::iter;
 
-use proc_macro::{TokenStream, TokenTree};
+use super::pm::{TokenStream, TokenTree};
 
 pub(crate) trait ToTokens {
     fn to_tokens(&self, tokens: &mut TokenStream);
@@ -0,0 +1,97 @@
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+use std::iter::FromIterator;
+
+use super::pm::{Delimiter, Group, Ident, Punct, Spacing, Span, TokenStream, TokenTree};
+
+use super::{Error, Result, iter::TokenIter};
+
+pub(crate) fn tt_span(tt: Option<&TokenTree>) -> Span {
+    tt.map_or_else(Span::call_site, TokenTree::span)
+}
+
+pub(crate) fn parse_as_empty(tokens: &mut TokenIter) -> Result<()> {
+    match tokens.next() {
+        Some(tt) => bail!(tt.span(), "unexpected token: `{}`", tt),
+        None => Ok(()),
+    }
+}
+
+// (`#[cfg(<tokens>)]`, `#[cfg(not(<tokens>))]`)
+pub(crate) fn cfg_attrs(tokens: TokenStream) -> (TokenStream, TokenStream) {
+    let f = |tokens| {
+        let tokens = TokenStream::from_iter(vec![
+            TokenTree::Ident(Ident::new("cfg", Span::call_site())),
+            TokenTree::Group(Group::new(Delimiter::Parenthesis, tokens)),
+        ]);
+        TokenStream::from_iter(vec![
+            TokenTree::Punct(Punct::new('#', Spacing::Alone)),
+            TokenTree::Group(Group::new(Delimiter::Bracket, tokens)),
+        ])
+    };
+
+    let cfg_not = TokenTree::Group(Group::new(Delimiter::Parenthesis, tokens.clone()));
+    let cfg_not = TokenStream::from_iter(vec![
+        TokenTree::Ident(Ident::new("not", Span::call_site())),
+        cfg_not,
+    ]);
+
+    (f(tokens), f(cfg_not))
+}
+
+// Formats a cfg predicate like it is written in the source code, e.g.,
+// `all(unix, not(feature = "x"))`, unlike `TokenStream::to_string`, which
+// inserts spaces between all tokens.
+pub(crate) fn pretty_cfg(tokens: TokenStream) -> String {
+    let mut out = String::new();
+    for tt in tokens {
+        match tt {
+            TokenTree::Group(ref g) if g.delimiter() == Delimiter::Parenthesis => {
+                out.push('(');
+                out.push_str(pretty_cfg(g.stream()).trim_end_matches(", "));
+                out.push(')');
+            }
+            TokenTree::Punct(ref p) if p.as_char() == ',' => out.push_str(", "),
+            TokenTree::Punct(ref p) if p.as_char() == '=' => out.push_str(" = "),
+            tt => out.push_str(&tt.to_string()),
+        }
+    }
+    out
+}
+
+// Sets the span of all tokens (including nested tokens) to the given span.
+pub(crate) fn respan(tokens: TokenStream, span: Span) -> TokenStream {
+    tokens
+        .into_iter()
+        .map(|mut tt| {
+            if let TokenTree::Group(ref mut g) = tt {
+                *g = Group::new(g.delimiter(), respan(g.stream(), span));
+            }
+            tt.set_span(span);
+            tt
+        })
+        .collect()
+}
+
+// Replaces identifiers (including nested ones) in `tokens` with the given tokens.
+pub(crate) fn interpolate(tokens: TokenStream, vars: &[(&str, &TokenStream)]) -> TokenStream {
+    let mut out = TokenStream::new();
+    for tt in tokens {
+        match tt {
+            TokenTree::Ident(ref i) => {
+                let s = i.to_string();
+                match vars.iter().find(|(name, _)| *name == s) {
+                    Some((_, value)) => out.extend((*value).clone()),
+                    None => out.extend(Some(tt.clone())),
+                }
+            }
+            TokenTree::Group(ref g) => {
+                let mut group = Group::new(g.delimiter(), interpolate(g.stream(), vars));
+                group.set_span(g.span());
+                out.extend(Some(TokenTree::Group(group)));
+            }
+            _ => out.extend(Some(tt)),
+        }
+    }
+    out
+}
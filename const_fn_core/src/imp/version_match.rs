@@ -0,0 +1,165 @@
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+use std::iter::FromIterator;
+
+use super::pm::{Delimiter, Group, Ident, Punct, Spacing, Span, TokenStream, TokenTree};
+
+use super::{
+    Cond, Error, Result,
+    iter::TokenIter,
+    parse_arg,
+    utils::{cfg_attrs, tt_span},
+};
+
+// Used to apply `#[cfg(...)]` to all items in an arm at once.
+const IDENTITY: &str = "__const_fn_version_match_identity";
+
+struct Arm {
+    // `None` if `_`
+    cond: Option<TokenStream>,
+    body: TokenStream,
+}
+
+pub(crate) fn expand(input: TokenStream) -> Result<TokenStream> {
+    let arms = parse_arms(input)?;
+
+    // Predicates of the previous `cfg(...)` arms; an arm is selected only if
+    // none of them is true.
+    let mut prev = vec![];
+    let mut outputs = vec![];
+    for arm in arms {
+        let cond = match arm.cond {
+            Some(cond) => parse_arg(cond)?.eval()?,
+            None => Cond::Bool(true),
+        };
+        match cond {
+            Cond::Bool(false) => {}
+            Cond::Bool(true) => {
+                outputs.push((not_any(&prev), arm.body));
+                break;
+            }
+            Cond::Cfg(cfg) => {
+                let pred = match not_any(&prev) {
+                    Some(not_any) => all(vec![cfg.clone(), not_any]),
+                    None => cfg.clone(),
+                };
+                outputs.push((Some(pred), arm.body));
+                prev.push(cfg);
+            }
+        }
+    }
+
+    let mut tokens = TokenStream::new();
+    if outputs.iter().all(|(pred, _)| pred.is_none()) {
+        // All conditions have been evaluated, so the selected arm (if any)
+        // can be emitted as-is. This also works in impl and trait blocks.
+        tokens.extend(outputs.into_iter().map(|(_, body)| body));
+        return Ok(tokens);
+    }
+    tokens.extend(identity_macro());
+    for (pred, body) in outputs {
+        if let Some(pred) = pred {
+            tokens.extend(cfg_attrs(pred).0);
+        }
+        tokens.extend(vec![
+            TokenTree::Ident(Ident::new(IDENTITY, Span::call_site())),
+            TokenTree::Punct(Punct::new('!', Spacing::Alone)),
+            TokenTree::Group(Group::new(Delimiter::Brace, body)),
+        ]);
+    }
+    Ok(tokens)
+}
+
+fn parse_arms(input: TokenStream) -> Result<Vec<Arm>> {
+    let iter = &mut TokenIter::new(input);
+    let mut arms: Vec<Arm> = vec![];
+    while iter.peek().is_some() {
+        if let Some(prev) = arms.last() {
+            if prev.cond.is_none() {
+                bail!(tt_span(iter.peek()), "`_` arm must be the last arm");
+            }
+        }
+
+        let mut cond = vec![];
+        let arrow_span = loop {
+            match iter.next() {
+                Some(TokenTree::Punct(ref p)) if p.as_char() == '=' && is_gt(iter.peek()) => {
+                    iter.next();
+                    break p.span();
+                }
+                Some(tt) => cond.push(tt),
+                None => bail!(tt_span(cond.last()), "expected `=>`"),
+            }
+        };
+        if cond.is_empty() {
+            bail!(arrow_span, "expected condition or `_` before `=>`");
+        }
+        let is_wildcard = match &*cond {
+            [TokenTree::Ident(i)] => i.to_string() == "_",
+            _ => false,
+        };
+        let cond = if is_wildcard { None } else { Some(TokenStream::from_iter(cond)) };
+
+        let body = match iter.next() {
+            Some(TokenTree::Group(ref g)) if g.delimiter() == Delimiter::Brace => g.stream(),
+            tt => bail!(tt_span(tt.as_ref()), "expected `{`"),
+        };
+        match iter.peek() {
+            Some(TokenTree::Punct(p)) if p.as_char() == ',' => {
+                iter.next();
+            }
+            _ => {}
+        }
+
+        arms.push(Arm { cond, body });
+    }
+    if arms.is_empty() {
+        bail!(Span::call_site(), "expected at least one arm");
+    }
+    Ok(arms)
+}
+
+fn is_gt(tt: Option<&TokenTree>) -> bool {
+    match tt {
+        Some(TokenTree::Punct(p)) => p.as_char() == '>',
+        _ => false,
+    }
+}
+
+// `not(any(<prev>,*))`
+fn not_any(prev: &[TokenStream]) -> Option<TokenStream> {
+    if prev.is_empty() {
+        return None;
+    }
+    let mut list = TokenStream::new();
+    for pred in prev {
+        list.extend(pred.clone());
+        list.extend(Some(TokenTree::Punct(Punct::new(',', Spacing::Alone))));
+    }
+    let any = call("any", list);
+    Some(call("not", any))
+}
+
+// `all(<preds>,*)`
+fn all(preds: Vec<TokenStream>) -> TokenStream {
+    let mut list = TokenStream::new();
+    for pred in preds {
+        list.extend(pred);
+        list.extend(Some(TokenTree::Punct(Punct::new(',', Spacing::Alone))));
+    }
+    call("all", list)
+}
+
+// `<name>(<args>)`
+fn call(name: &str, args: TokenStream) -> TokenStream {
+    TokenStream::from_iter(vec![
+        TokenTree::Ident(Ident::new(name, Span::call_site())),
+        TokenTree::Group(Group::new(Delimiter::Parenthesis, args)),
+    ])
+}
+
+// macro_rules! __const_fn_version_match_identity { ($($tt:tt)*) => { $($tt)* }; }
+fn identity_macro() -> TokenStream {
+    let s = format!("macro_rules! {} {{ ($($tt:tt)*) => {{ $($tt)* }}; }}", IDENTITY);
+    s.parse().unwrap()
+}
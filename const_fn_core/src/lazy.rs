@@ -0,0 +1,47 @@
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+// The wrapper of statics generated by `const_or_lazy` when the condition does
+// not hold. It lives here rather than in the generated code so that the unsafe
+// code is not compiled in the user's crate, which may use
+// `#![forbid(unsafe_code)]`.
+//
+// The wrapper is constructed with a struct expression in the static, because
+// a `const fn` constructor would require Rust 1.32 to compile this crate
+// (`Once::new` and `UnsafeCell::new` are const since 1.32), and fn pointers
+// cannot be arguments of `const fn` before Rust 1.61. `OnceLock` is not
+// available on older compilers, where this is needed most.
+
+use std::{cell::UnsafeCell, ops::Deref, sync::Once};
+
+#[doc(hidden)]
+pub struct Lazy<T> {
+    pub once: Once,
+    pub value: UnsafeCell<Option<T>>,
+    pub init: fn() -> T,
+}
+
+// SAFETY: `value` is written only once, in `call_once`, before any read, and
+// other threads wait for `call_once` to complete. `T` does not need to be
+// `Send`, as this type is only constructed in statics, which are never dropped.
+// This is the same requirement as statics.
+unsafe impl<T: Sync> Sync for Lazy<T> {}
+
+impl<T> Deref for Lazy<T> {
+    type Target = T;
+    #[inline]
+    fn deref(&self) -> &T {
+        self.once.call_once(|| {
+            let value = (self.init)();
+            // SAFETY: no reference to `value` exists before `call_once`
+            // completes, and `call_once` runs this closure at most once.
+            unsafe { *self.value.get() = Some(value) }
+        });
+        // SAFETY: `value` is not written after `call_once` completes.
+        match unsafe { &*self.value.get() } {
+            Some(value) => value,
+            // `init` panicked and the `Once` is poisoned, so `call_once` panics
+            // instead of returning.
+            None => unreachable!(),
+        }
+    }
+}
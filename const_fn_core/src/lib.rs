@@ -0,0 +1,135 @@
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+/*!
+The implementation of [`const_fn`](https://docs.rs/const_fn), usable from other
+procedural macros.
+
+Each function in this crate expands the corresponding macro of `const_fn` from
+token streams, and returns `compile_error!` on error. The conditions are
+evaluated in the same way as `const_fn`, i.e., with the compiler version
+detected by the build script of this crate.
+
+This is useful, for example, when your macro generates functions that should be
+`const` under the same rules as functions written by hand with
+`#[const_fn]`.
+
+## Examples
+
+```no_run
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+
+// In your procedural macro:
+fn generate() -> TokenStream {
+    let func: TokenStream = "pub fn get() -> u8 { 1 }".parse().unwrap();
+    // Same as `#[const_fn("1.61")] pub fn get() -> u8 { 1 }`
+    const_fn_core::const_fn(r#""1.61""#.parse().unwrap(), func)
+}
+```
+
+Note that, like the `proc_macro` API itself, these functions can only be
+called while a procedural macro is being executed.
+
+## Optional features
+
+- **`proc-macro2`** — Provide the same functions with the token types of
+  [`proc-macro2`](https://docs.rs/proc-macro2) in the `proc_macro2`
+  module. This is convenient for macros written with `proc-macro2` (e.g.,
+  macros using `syn` and `quote`), and allows calling the functions of this crate
+  outside of procedural macros, e.g., in unit tests. Note that `proc-macro2`
+  requires a newer compiler than the MSRV of this crate.
+*/
+
+#![doc(test(
+    no_crate_inject,
+    attr(allow(
+        dead_code,
+        unused_variables,
+        clippy::undocumented_unsafe_blocks,
+        clippy::unused_trait_names,
+    ))
+))]
+// `deny` instead of `forbid` because the runtime support of `const_or_lazy`
+// needs unsafe code.
+#![deny(unsafe_code)]
+// proc_macro2::Ident can be compared with str, but proc_macro::Ident cannot.
+#![cfg_attr(any(feature = "proc-macro2", test), allow(clippy::cmp_owned))]
+
+// older compilers require explicit `extern crate`.
+#[allow(unused_extern_crates)]
+extern crate proc_macro;
+
+#[allow(unsafe_code)]
+mod lazy;
+mod manifest;
+
+// The implementation is compiled once for each token type. Unit tests use
+// proc-macro2 because proc_macro only works inside procedural macros.
+#[path = "imp"]
+#[allow(clippy::duplicate_mod)]
+mod with_proc_macro {
+    #[cfg(not(test))]
+    use proc_macro as pm;
+    #[cfg(test)]
+    use proc_macro2 as pm;
+
+    #[path = "mod.rs"]
+    pub(crate) mod imp;
+}
+#[cfg(all(feature = "proc-macro2", not(test)))]
+#[path = "imp"]
+#[allow(clippy::duplicate_mod)]
+mod with_proc_macro2 {
+    use proc_macro2 as pm;
+
+    #[path = "mod.rs"]
+    pub(crate) mod imp;
+}
+
+pub use crate::with_proc_macro::imp::{
+    assert_const, cfg_rustc, const_fn, const_or_lazy, const_test, parse_condition, rustc_version,
+    version_match,
+};
+
+// Not public API. Used by the code generated by `const_or_lazy`.
+#[doc(hidden)]
+pub mod __private {
+    pub use crate::lazy::Lazy;
+}
+
+/// The same functions as the crate root, with the token types of
+/// [`proc-macro2`](https://docs.rs/proc-macro2).
+///
+/// Unlike the functions in the crate root, these functions can also be called
+/// outside of procedural macros.
+///
+/// This module is only available with the `proc-macro2` feature.
+#[cfg(all(feature = "proc-macro2", not(test)))]
+pub mod proc_macro2 {
+    pub use crate::with_proc_macro2::imp::{
+        assert_const, cfg_rustc, const_fn, const_or_lazy, const_test, parse_condition,
+        rustc_version, version_match,
+    };
+}
+
+/// The condition of a `#[const_fn(<args>)]` attribute, as returned by
+/// [`parse_condition`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Condition {
+    /// `#[const_fn]`
+    Always,
+    /// `#[const_fn("<major>.<minor>")]`
+    Version {
+        /// The major version.
+        major: u32,
+        /// The minor version.
+        minor: u32,
+    },
+    /// `#[const_fn(nightly)]`
+    Nightly,
+    /// `#[const_fn(feature = "<name>")]`, with the name of the feature.
+    Feature(String),
+    /// `#[const_fn(cfg(<pred>))]`, with the predicate.
+    Cfg(String),
+}
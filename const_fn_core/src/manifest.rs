@@ -0,0 +1,474 @@
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+// Reader for the `[package.metadata.const_fn]` table in Cargo.toml.
+//
+// This is not a complete TOML parser. It only understands what is needed to
+// read `key = "value"` and `key = true|false` pairs (with bare, quoted, or dotted keys, and
+// in inline tables) in tables under `package.metadata.const_fn`, and skips everything else.
+// Unsupported syntax in these tables (e.g., multi-line strings and values, and arrays of
+// tables) is an error rather than being ignored.
+
+use std::{
+    cell::RefCell,
+    env, fs,
+    path::{Path, PathBuf},
+    rc::Rc,
+    time::SystemTime,
+};
+
+const PREFIX: &str = "package.metadata.const_fn";
+
+thread_local! {
+    // Overrides `CARGO_MANIFEST_DIR` while `with_manifest_dir` is running.
+    static MANIFEST_DIR: RefCell<Option<PathBuf>> = RefCell::new(None);
+    // The metadata read by `read`, per manifest directory, with the
+    // modification time of Cargo.toml at the time it was read.
+    static CACHE: RefCell<Vec<(PathBuf, Option<SystemTime>, Result<Rc<Metadata>, String>)>> =
+        RefCell::new(vec![]);
+    // The path of Cargo.toml read by `read` since the last call to `take_read`.
+    static READ: RefCell<Option<PathBuf>> = RefCell::new(None);
+}
+
+pub(crate) struct Metadata {
+    // [package.metadata.const_fn.aliases]
+    aliases: Vec<(String, String)>,
+    // package.metadata.const_fn.strict
+    pub(crate) strict: bool,
+    // package.metadata.const_fn.allow-lints
+    pub(crate) allow_lints: bool,
+}
+
+enum Value {
+    String(String),
+    Bool(bool),
+    // `{ key = value, ... }`
+    Table(Vec<(String, Value)>),
+    // Values not used by this crate, e.g., numbers and arrays.
+    Other,
+}
+
+impl Metadata {
+    pub(crate) fn alias(&self, name: &str) -> Option<&str> {
+        self.aliases.iter().find(|(k, _)| k == name).map(|(_, v)| &**v)
+    }
+}
+
+// Calls `f` with the metadata read from `dir` instead of `CARGO_MANIFEST_DIR`.
+pub(crate) fn with_manifest_dir<T>(dir: &Path, f: impl FnOnce() -> T) -> T {
+    let prev = MANIFEST_DIR.with(|d| d.replace(Some(dir.to_owned())));
+    let result = f();
+    MANIFEST_DIR.with(|d| *d.borrow_mut() = prev);
+    result
+}
+
+// Reads the metadata of the crate currently being compiled.
+//
+// The result is cached per manifest directory, as this is called for each
+// expansion that needs the metadata. Cargo.toml is re-read if it has been
+// modified, e.g., in a long-running proc-macro server of an IDE.
+pub(crate) fn read() -> Result<Rc<Metadata>, String> {
+    let dir = match MANIFEST_DIR.with(|d| d.borrow().clone()) {
+        Some(dir) => dir,
+        None => {
+            PathBuf::from(env::var_os("CARGO_MANIFEST_DIR").ok_or("CARGO_MANIFEST_DIR not set")?)
+        }
+    };
+    let path = dir.join("Cargo.toml");
+    let modified = fs::metadata(&path).and_then(|m| m.modified()).ok();
+    CACHE.with(|cache| {
+        let mut cache = cache.borrow_mut();
+        // If the modification time is not available, Cargo.toml is always re-read.
+        if modified.is_some() {
+            if let Some(entry) = cache.iter().find(|e| e.0 == dir && e.1 == modified) {
+                if entry.2.is_ok() {
+                    READ.with(|r| *r.borrow_mut() = Some(path.clone()));
+                }
+                return entry.2.clone();
+            }
+        }
+        let result = fs::read_to_string(&path)
+            .map_err(|e| format!("failed to read {}: {}", path.display(), e))
+            .and_then(|s| {
+                parse(&s).map_err(|e| format!("failed to parse {}: {}", path.display(), e))
+            })
+            .map(Rc::new);
+        if result.is_ok() {
+            READ.with(|r| *r.borrow_mut() = Some(path.clone()));
+        }
+        cache.retain(|e| e.0 != dir);
+        cache.push((dir, modified, result.clone()));
+        result
+    })
+}
+
+// Returns the path of Cargo.toml if it has been read since the last call.
+//
+// rustc does not know that the expansion depends on Cargo.toml, so the macros
+// include it in their expansion to make it an input of the crate.
+pub(crate) fn take_read() -> Option<PathBuf> {
+    READ.with(|r| r.borrow_mut().take())
+}
+
+fn parse(s: &str) -> Result<Metadata, String> {
+    let mut metadata = Metadata { aliases: vec![], strict: false, allow_lints: false };
+    for (key, value) in entries(s)? {
+        let short_key = &key[PREFIX.len()..];
+        if short_key.starts_with(".aliases.") {
+            let name = &short_key[".aliases.".len()..];
+            match value {
+                // `a.b = "..."` or `a = { b = "..." }` is not a string value of `a`.
+                Value::String(_) if name.contains('.') => {
+                    let name = &name[..name.find('.').unwrap()];
+                    return Err(format!(
+                        "expected string in value of `{}.aliases.{}`",
+                        PREFIX, name
+                    ));
+                }
+                Value::String(value) => metadata.aliases.push((name.to_owned(), value)),
+                _ => return Err(format!("expected string in value of `{}`", key)),
+            }
+        } else if short_key == ".strict" || short_key == ".allow-lints" {
+            let value = match value {
+                Value::Bool(value) => value,
+                _ => return Err(format!("expected boolean in value of `{}`", key)),
+            };
+            if short_key == ".strict" {
+                metadata.strict = value;
+            } else {
+                metadata.allow_lints = value;
+            }
+        }
+        // Ignore unknown keys for forward compatibility.
+    }
+    Ok(metadata)
+}
+
+// Returns `(full.dotted.key, value)` pairs under `package.metadata.const_fn`.
+fn entries(s: &str) -> Result<Vec<(String, Value)>, String> {
+    let mut entries = vec![];
+    let mut table = String::new();
+    // The delimiter of the multi-line string we are currently in, if any.
+    let mut multi_line: Option<&str> = None;
+    for (i, line) in s.lines().enumerate() {
+        let line = line.trim();
+        if let Some(delim) = multi_line {
+            if line.contains(delim) {
+                multi_line = None;
+            }
+            continue;
+        }
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if line.starts_with('[') {
+            let end = line.find(']').ok_or_else(|| format!("line {}: expected `]`", i + 1))?;
+            table = parse_key(line[1..end].trim_matches('['))?;
+            if line.starts_with("[[") && is_target(&table) {
+                return Err(format!("line {}: arrays of tables are not supported", i + 1));
+            }
+            continue;
+        }
+        // Tables like `[package.metadata]` may contain target keys as dotted keys.
+        let key = if is_target(&table) {
+            let eq = line.find('=').ok_or_else(|| format!("line {}: expected `=`", i + 1))?;
+            Some((format!("{}.{}", table, parse_key(&line[..eq])?), eq))
+        } else if is_parent(&table) {
+            line.find('=').and_then(|eq| {
+                let key = parse_key(&line[..eq]).ok()?;
+                let key = if table.is_empty() { key } else { format!("{}.{}", table, key) };
+                Some((key, eq))
+            })
+        } else {
+            None
+        };
+        let (key, eq) = match key {
+            Some((key, eq)) if is_target(&key) => (key, eq),
+            // e.g., `const_fn = { ... }` in `[package.metadata]`
+            Some((key, eq)) if is_parent(&key) && line[eq + 1..].trim_start().starts_with('{') => {
+                (key, eq)
+            }
+            _ => {
+                for &delim in &["\"\"\"", "'''"] {
+                    if line.matches(delim).count() % 2 == 1 {
+                        multi_line = Some(delim);
+                    }
+                }
+                continue;
+            }
+        };
+        let value = parse_value(line[eq + 1..].trim())
+            .map_err(|e| format!("line {}: {} in value of `{}`", i + 1, e, key))?;
+        flatten(key, value, &mut entries);
+    }
+    Ok(entries)
+}
+
+// Pushes the target entries in `key = value`, with inline tables expanded to
+// dotted keys.
+fn flatten(key: String, value: Value, entries: &mut Vec<(String, Value)>) {
+    match value {
+        Value::Table(table) => {
+            for (k, v) in table {
+                flatten(format!("{}.{}", key, k), v, entries);
+            }
+        }
+        value => {
+            if is_target(&key) {
+                entries.push((key, value));
+            }
+        }
+    }
+}
+
+// Returns `true` if `table` is an ancestor of `package.metadata.const_fn`.
+fn is_parent(table: &str) -> bool {
+    table.is_empty() || PREFIX.starts_with(table) && PREFIX[table.len()..].starts_with('.')
+}
+
+fn is_target(key: &str) -> bool {
+    key.starts_with(PREFIX) && (key.len() == PREFIX.len() || key[PREFIX.len()..].starts_with('.'))
+}
+
+// Normalizes `a . "b" . c` to `a.b.c`.
+fn parse_key(s: &str) -> Result<String, String> {
+    let mut key = String::new();
+    for part in s.split('.') {
+        let part = part.trim();
+        let part = if part.len() >= 2
+            && (part.starts_with('"') && part.ends_with('"')
+                || part.starts_with('\'') && part.ends_with('\''))
+        {
+            &part[1..part.len() - 1]
+        } else {
+            part
+        };
+        if part.is_empty() {
+            return Err(format!("invalid key `{}`", s.trim()));
+        }
+        if !key.is_empty() {
+            key.push('.');
+        }
+        key.push_str(part);
+    }
+    Ok(key)
+}
+
+fn parse_value(s: &str) -> Result<Value, String> {
+    let (value, rest) = value(s)?;
+    let rest = rest.trim();
+    if rest.is_empty() || rest.starts_with('#') {
+        Ok(value)
+    } else {
+        Err(format!("unexpected input `{}`", rest))
+    }
+}
+
+// Parses a value at the start of `s`, and returns it with the rest of `s`.
+fn value(s: &str) -> Result<(Value, &str), String> {
+    if s.starts_with("\"\"\"") || s.starts_with("'''") {
+        return Err("multi-line strings are not supported".to_owned());
+    }
+    let mut chars = s.char_indices();
+    Ok(match chars.next() {
+        Some((_, '"')) => {
+            let mut value = String::new();
+            loop {
+                match chars.next() {
+                    Some((i, '"')) => break (Value::String(value), &s[i + 1..]),
+                    Some((_, '\\')) => value.push(match chars.next() {
+                        Some((_, '"')) => '"',
+                        Some((_, '\\')) => '\\',
+                        Some((_, 'n')) => '\n',
+                        Some((_, 'r')) => '\r',
+                        Some((_, 't')) => '\t',
+                        Some((_, c)) => return Err(format!("unsupported escape `\\{}`", c)),
+                        None => return Err("unterminated string".to_owned()),
+                    }),
+                    Some((_, c)) => value.push(c),
+                    None => return Err("unterminated string".to_owned()),
+                }
+            }
+        }
+        Some((_, '\'')) => match s[1..].find('\'') {
+            Some(end) => (Value::String(s[1..=end].to_owned()), &s[end + 2..]),
+            None => return Err("unterminated string".to_owned()),
+        },
+        Some((_, '{')) => {
+            let mut table = vec![];
+            let rest = list(&s[1..], '}', |s| {
+                let eq = s.find('=').ok_or("expected `=` in inline table")?;
+                let (value, rest) = value(s[eq + 1..].trim_start())?;
+                table.push((parse_key(&s[..eq])?, value));
+                Ok(rest)
+            })?;
+            (Value::Table(table), rest)
+        }
+        Some((_, '[')) => (Value::Other, list(&s[1..], ']', |s| value(s).map(|(_, rest)| rest))?),
+        _ => {
+            // `true`, `false`, numbers, and dates
+            let end = s
+                .find(|c: char| c.is_whitespace() || c == ',' || c == '}' || c == ']' || c == '#')
+                .unwrap_or(s.len());
+            let value = match &s[..end] {
+                "true" => Value::Bool(true),
+                "false" => Value::Bool(false),
+                "" => return Err("expected value".to_owned()),
+                _ => Value::Other,
+            };
+            (value, &s[end..])
+        }
+    })
+}
+
+// Parses comma-separated elements until `close`, and returns the rest of `s`.
+fn list<'a>(
+    s: &'a str,
+    close: char,
+    mut f: impl FnMut(&'a str) -> Result<&'a str, String>,
+) -> Result<&'a str, String> {
+    let is_eol = |s: &str| s.is_empty() || s.starts_with('#');
+    let mut s = s.trim_start();
+    loop {
+        if s.starts_with(close) {
+            return Ok(&s[1..]);
+        }
+        if is_eol(s) {
+            return Err(format!(
+                "expected `{}`; values spanning multiple lines are not supported",
+                close
+            ));
+        }
+        let rest = f(s)?.trim_start();
+        if rest.starts_with(',') {
+            s = rest[1..].trim_start();
+        } else if rest.starts_with(close) {
+            return Ok(&rest[1..]);
+        } else if is_eol(rest) {
+            return Err(format!(
+                "expected `{}`; values spanning multiple lines are not supported",
+                close
+            ));
+        } else {
+            return Err(format!("expected `,` or `{}`", close));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_metadata() {
+        let s = r#"
+[package]
+name = "a"
+keywords = [
+  "a",
+]
+description = """
+[package.metadata.const_fn.aliases]
+fake = "1.0"
+"""
+
+[package.metadata.const_fn]
+strict = true # comment
+allow-lints = true
+
+[package.metadata.const_fn.aliases]
+a = "1.61"
+"b" = 'cfg(feature = "x")'
+c = "cfg(feature = \"y\")"
+
+[package.metadata]
+const_fn.aliases.d = "nightly"
+docs.rs = { targets = ["x86_64-unknown-linux-gnu"], rustdoc-args = [] }
+
+[package.metadata.const_fn.aliases]
+e = "cfg(a)" # comment
+
+[dependencies]
+a = "1"
+"#;
+        let metadata = parse(s).unwrap();
+        assert!(metadata.strict);
+        assert!(metadata.allow_lints);
+        assert_eq!(metadata.alias("a"), Some("1.61"));
+        assert_eq!(metadata.alias("b"), Some("cfg(feature = \"x\")"));
+        assert_eq!(metadata.alias("c"), Some("cfg(feature = \"y\")"));
+        assert_eq!(metadata.alias("d"), Some("nightly"));
+        assert_eq!(metadata.alias("e"), Some("cfg(a)"));
+        assert_eq!(metadata.alias("fake"), None);
+
+        let metadata = parse("[package]\nname = \"a\"\n").unwrap();
+        assert!(!metadata.strict);
+        assert!(!metadata.allow_lints);
+
+        // inline tables
+        let s = r#"
+[package.metadata]
+const_fn = { strict = true, aliases = { a = "1.61", "b" = 'nightly' }, future = [1, { x = 2 }] }
+
+[package.metadata.const_fn]
+aliases = {}
+"#;
+        let metadata = parse(s).unwrap();
+        assert!(metadata.strict);
+        assert_eq!(metadata.alias("a"), Some("1.61"));
+        assert_eq!(metadata.alias("b"), Some("nightly"));
+        let metadata = parse("[package]\nmetadata.const_fn = { aliases.a = \"1.61\" }\n").unwrap();
+        assert_eq!(metadata.alias("a"), Some("1.61"));
+        let metadata =
+            parse("[package.metadata.const_fn]\naliases = { a = \"1.61\" } # comment\n").unwrap();
+        assert_eq!(metadata.alias("a"), Some("1.61"));
+    }
+
+    #[test]
+    fn parse_metadata_err() {
+        for s in &[
+            "[package.metadata.const_fn]\nstrict = \"true\"",
+            "[package.metadata.const_fn]\nallow-lints = \"true\"",
+            "[package.metadata.const_fn.aliases]\na = true",
+            "[package.metadata.const_fn.aliases]\na = \"1.61",
+            "[package.metadata.const_fn.aliases]\na = \"1.61\" b",
+            "[package.metadata.const_fn.aliases]\na",
+            "[package.metadata.const_fn]\naliases = { a = \"1.61\"",
+            "[package.metadata.const_fn]\naliases = { a = \"1.61\" b = \"1.62\" }",
+            "[package.metadata.const_fn]\naliases = { a = 1 }",
+            "[package.metadata]\nconst_fn = { strict = \"true\" }",
+            // arrays
+            "[package.metadata.const_fn.aliases]\na = [\"1.61\"]",
+            "[package.metadata.const_fn]\nstrict = [true]",
+            "[package.metadata.const_fn]\nfuture = [\n  1,\n]",
+            "[[package.metadata.const_fn.aliases]]\na = \"1.61\"",
+            // inline tables
+            "[package.metadata.const_fn.aliases]\na = { b = \"1.61\" }",
+            "[package.metadata.const_fn]\naliases = { a = { b = \"1.61\" } }",
+            "[package.metadata.const_fn]\naliases = {\n  a = \"1.61\",\n}",
+            "[package.metadata.const_fn.aliases.a]\nb = \"1.61\"",
+            // multi-line strings
+            "[package.metadata.const_fn.aliases]\na = \"\"\"1.61\"\"\"",
+            "[package.metadata.const_fn.aliases]\na = '''\n1.61'''",
+        ] {
+            assert!(parse(s).is_err(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn read_cache() {
+        let dir = env::temp_dir().join(format!("const_fn_core-read_cache-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("Cargo.toml"),
+            "[package.metadata.const_fn]
+strict = true
+",
+        )
+        .unwrap();
+        let (a, b) = with_manifest_dir(&dir, || (read().unwrap(), read().unwrap()));
+        assert!(a.strict);
+        assert!(Rc::ptr_eq(&a, &b));
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(with_manifest_dir(&dir, read).is_err());
+    }
+}
@@ -0,0 +1,30 @@
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+// Checks that the parsers of the arguments and inputs never panic.
+//
+// The first line of the input is used as the arguments and the rest as the
+// item, e.g., `"1.61", export_flag\npub fn f() {}`.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use proc_macro2::TokenStream;
+
+fuzz_target!(|data: &str| {
+    let mut lines = data.splitn(2, '\n');
+    let args = match lines.next().unwrap_or_default().parse::<TokenStream>() {
+        Ok(args) => args,
+        Err(_) => return,
+    };
+    let input = match lines.next().unwrap_or_default().parse::<TokenStream>() {
+        Ok(input) => input,
+        Err(_) => return,
+    };
+
+    const_fn_core::proc_macro2::const_fn(args.clone(), input.clone());
+    const_fn_core::proc_macro2::cfg_rustc(args.clone(), input.clone());
+    const_fn_core::proc_macro2::const_test(args.clone(), input.clone());
+    const_fn_core::proc_macro2::const_or_lazy(args.clone(), input.clone());
+    const_fn_core::proc_macro2::version_match(input.clone());
+    const_fn_core::proc_macro2::assert_const(input);
+});
@@ -54,6 +54,327 @@ fn optional() {
 }
 ```
 
+### Aliases
+
+If the same condition is used in many places, you can give it a name in the
+`[package.metadata.const_fn.aliases]` table in your `Cargo.toml`. The value is
+a condition written in the same way as the attribute argument.
+
+```toml
+[package.metadata.const_fn.aliases]
+unstable_const = 'cfg(all(feature = "unstable", not(miri)))'
+```
+
+```
+# #[cfg(any(/* always false */))]
+// same as `#[const_fn(cfg(all(feature = "unstable", not(miri))))]`
+#[const_fn(alias = "unstable_const")]
+# fn _alias() { unimplemented!() }
+const fn alias() {
+    /* ... */
+}
+```
+
+Inline tables, such as `aliases = { unstable_const = '...' }` in the
+`[package.metadata.const_fn]` table, can also be used.
+
+When the expansion of a macro depends on `Cargo.toml` (e.g., an alias is
+used), it includes `Cargo.toml` with `include_bytes!`, so that changing
+`Cargo.toml` expands the macro again. This is not done for
+[`#[cfg_rustc]`](macro@cfg_rustc) and [`version_match!`](version_match), whose
+expansions can appear where no extra item is allowed, e.g., in trait
+implementations. After changing an alias used only by them, rebuild the crate
+for another reason, e.g., a change to its source files or
+`cargo clean -p <crate>`.
+
+### Overriding conditions
+
+The `CONST_FN_FORCE` environment variable overrides the conditions of all
+`#[const_fn]` attributes, which is useful, for example, for coverage builds
+(const-evaluated calls are not instrumented) or to debug const evaluation
+errors.
+
+- `CONST_FN_FORCE=always`: all functions are `const`.
+- `CONST_FN_FORCE=never`: all functions are non-`const`.
+- `CONST_FN_FORCE=auto` (default): respect the conditions.
+
+`--cfg const_fn_force="always"` and `--cfg const_fn_force="never"` in
+`RUSTFLAGS` have the same effect.
+
+### Tracing expansions
+
+To see why a function is or is not `const`, set the `CONST_FN_DEBUG`
+environment variable to a comma-separated list of function names (or `*` for
+all functions). Each matching `#[const_fn]` then prints the parsed condition,
+the compiler version it was compared against, the decision, and the expanded
+code to stderr:
+
+```sh
+CONST_FN_DEBUG=my_fn cargo build
+```
+
+```text
+[const_fn] my_crate::my_fn
+  arg: "1.61"
+  rustc: 1.83.0
+  decision: const
+  output: const fn my_fn() {}
+```
+
+Changing the variable causes the macros to be expanded again.
+
+### Build systems other than Cargo
+
+The version and channel of the compiler are detected by the build script of
+`const_fn_core`, which implements this crate. If the build script does not run (e.g., Bazel, Buck, meson), set
+the `CONST_FN_RUSTC_VERSION` environment variable to the compiler version
+(e.g., `CONST_FN_RUSTC_VERSION=1.83.0` or `CONST_FN_RUSTC_VERSION=1.85.0-nightly`)
+when compiling crates that use `#[const_fn]`. Otherwise, the compiler is
+assumed to be Rust 1.31 (stable) and a warning is printed to stderr when the
+macros expand.
+
+### Version cfgs for your build script
+
+[`const_fn_build`](https://docs.rs/const_fn_build) detects the compiler version
+and channel in the same way as this crate, and emits cfgs such as `rustc_1_61`
+(with the corresponding `rustc-check-cfg` declarations) from your build
+script, which can be used with `#[const_fn(cfg(...))]`.
+
+### Conditional items
+
+[`#[cfg_rustc]`](macro@cfg_rustc) keeps or removes any item (impls, `use`
+declarations, statics, etc.) depending on the same conditions as `#[const_fn]`.
+
+```
+use const_fn::cfg_rustc;
+
+// item is kept on specified version and later compiler
+#[cfg_rustc("1.61")]
+impl<T: Copy> Wrapper<T> {
+    const fn get(&self) -> T {
+        self.0
+    }
+}
+# struct Wrapper<T>(T);
+```
+
+### Multiple implementations
+
+[`version_match!`](version_match) selects the first arm whose condition holds, which
+is useful when more than two implementations are needed.
+
+```
+const_fn::version_match! {
+    "1.83" => {
+        pub const fn set(x: &mut u8, v: u8) {
+            *x = v;
+        }
+    },
+    _ => {
+        pub fn set(x: &mut u8, v: u8) {
+            *x = v;
+        }
+    },
+}
+```
+
+### Exporting constness
+
+With the `export_flag` option, `#[const_fn]` also generates a hidden constant
+named `__CONST_FN_IS_CONST_<name>` next to the function, with the same
+visibility as the function. Its value is `true` if the function is `const` on
+the current compiler, so downstream crates can check the decision made by this
+crate.
+
+```
+#[const_fn::const_fn("1.61", export_flag)]
+pub fn foo() {}
+
+// `true` on Rust 1.61 and later, `false` otherwise.
+let _: bool = __CONST_FN_IS_CONST_foo;
+```
+
+On methods, the constant is generated as an associated constant, so this option
+cannot be used in trait implementations.
+
+### Strict mode
+
+Making a public function `const` only on some compilers is a semver hazard:
+users of your crate may rely on the function being `const` with their compiler,
+and then their code will fail to compile with another one. Setting `strict` in
+the `[package.metadata.const_fn]` table in your `Cargo.toml` rejects public
+functions whose constness depends on the compiler version, the nightly
+compiler, or `cfg` other than `feature = "..."`.
+
+```toml
+[package.metadata.const_fn]
+strict = true
+```
+
+Conditions controlled by features, which users opt into explicitly, are still
+allowed, as are non-public functions (including `pub(crate)` ones).
+
+### Documentation
+
+With the `doc` option, `#[const_fn]` appends a paragraph describing the
+condition to the documentation of the function, such as "This function is
+`const` on Rust 1.61 and later." The paragraph is only added to functions that
+have doc comments. In addition, the `const` variant of the function is always
+rendered by rustdoc (`cfg(doc)`), regardless of the compiler used to build the
+documentation.
+
+```
+/// Returns zero.
+#[const_fn::const_fn("1.61", doc)]
+pub const fn zero() -> u8 {
+    0
+}
+```
+
+### Type-checking both variants
+
+Only one of the `const` and non-`const` variants is compiled, so errors in the
+variant that is not compiled (e.g., a body that is not valid in `const`
+contexts, or attributes added by `with(...)` or `without(...)`) only show up
+on configurations where that variant is used. The `check_both` option also
+generates a hidden private copy of the inactive variant, so that both variants
+are type-checked in every build: when the function is `const`, a non-`const`
+copy, and with a `cfg(...)` condition, a `const` copy when the function is not
+`const`.
+
+For conditions that are evaluated when the macro expands (Rust versions,
+`nightly`, and aliases of them), no `const` copy is generated when the
+condition is false, since the current compiler may not accept the body in
+`const` contexts; the `const` variant is only checked on compilers that meet
+the condition.
+
+```
+#[const_fn::const_fn(cfg(feature = "const"), check_both)]
+pub fn foo() {}
+```
+
+Like `export_flag`, this option cannot be used in trait implementations.
+
+### Attributes for one variant
+
+Attributes listed in the `with(...)` option are added only to the `const`
+variant of the function, and those in `without(...)` only to the non-`const`
+variant. This is useful for attributes that only make sense on a `const fn`,
+such as `#[rustc_const_stable]` in std-style crates.
+
+```
+#[const_fn::const_fn(
+    cfg(feature = "const"),
+    with(#[must_use = "the result is evaluated at compile time"]),
+    without(#[inline]),
+)]
+pub fn foo() -> u8 {
+    1
+}
+```
+
+### Lints on the generated variants
+
+The non-`const` variant of a function and the copy generated by `check_both`
+are flagged by `clippy::missing_const_for_fn`. Setting `allow-lints` in the
+`[package.metadata.const_fn]` table in your `Cargo.toml` adds
+`#[allow(clippy::missing_const_for_fn)]` to these variants only. Other lints,
+such as `dead_code` and the documentation lints, are reported once for the
+function, as only one of its `const` and non-`const` variants is compiled.
+
+```toml
+[package.metadata.const_fn]
+allow-lints = true
+```
+
+### Testing constness
+
+[`assert_const!`](assert_const) evaluates an expression at compile time if the
+given condition holds, and at runtime otherwise, which is useful to test that
+functions are really `const` when expected. Note that the expression is only
+run at runtime when the condition is false.
+
+```
+use const_fn::{assert_const, const_fn};
+
+#[const_fn("1.61")]
+fn foo() -> u8 {
+    1
+}
+
+// `foo()` is evaluated at compile time on Rust 1.61 and later.
+assert_const!("1.61", foo());
+```
+
+Similarly, [`#[const_test]`](macro@const_test) runs a `#[test]` function as a normal test and
+also evaluates its body at compile time if the given condition holds.
+
+### Conditionally const statics
+
+[`#[const_or_lazy]`](macro@const_or_lazy) emits a `static` as-is if the given
+condition holds, and otherwise initializes it lazily on first access. The
+lazily initialized static requires Rust 1.32 and uses a wrapper type defined in
+`const_fn_core`, so add it to your dependencies:
+
+```toml
+[dependencies]
+const_fn = "0.4"
+const_fn_core = "0.1"
+```
+
+```
+use const_fn::{const_fn, const_or_lazy};
+
+#[const_fn("1.61")]
+fn make() -> Option<u8> {
+    Some(1)
+}
+
+#[const_or_lazy("1.61")]
+static VALUE: Option<u8> = make();
+```
+
+### Use from other procedural macros
+
+The implementation of this crate is available as a normal library,
+[`const_fn_core`](https://docs.rs/const_fn_core), so procedural macros that
+generate functions can make them `const` under the same conditions.
+
+### Auditing conditions
+
+The `cargo-const-fn` subcommand lists every `#[const_fn]` function in a package
+or workspace with its condition and visibility, and reports conditions that
+always hold on the `rust-version` of the package. Attributes behind `cfg_attr`
+are reported with their predicate, and are never reported as redundant:
+
+```sh
+cargo install cargo-const-fn
+cargo const-fn audit
+# Also report which functions are const on Rust 1.61, in JSON
+cargo const-fn audit --rustc-version 1.61 --format json
+```
+
+After raising the `rust-version` of your package, `cargo const-fn migrate`
+replaces `#[const_fn("1.XX")]` attributes that always hold on the new
+`rust-version` with the `const` keyword, keeping the rest of the source code
+as is, and removes `use const_fn::const_fn;` that is no longer used.
+Attributes with options or other kinds of conditions are left untouched. Use
+`--dry-run` to print the changes as a diff instead of writing them.
+
+`cargo const-fn candidates` lists non-`const` functions that could be
+`const fn`, with the Rust version their signature and body require and the
+suggested attribute, e.g., `#[const_fn("1.46")]` for a function using `match`,
+or just `const fn` if the `rust-version` of the package is new enough.
+`--rustc-version 1.XX` limits the list to functions that can be `const fn` on
+that version. The check works on tokens, with its own table of the
+constructs, macros, and standard library functions allowed in `const fn` and
+the versions that allowed them (`#[const_fn]` itself does not check function
+bodies). As there is no type information, methods of the standard library are
+only recognized on parameters and variables whose types are written as, e.g.,
+`&str` or `u32`, and functions that take or create values of types that may
+have destructors are not listed. Verify the suggestions by compiling them on
+the suggested version.
+
 <!--
 TODO: document the behavior on the version on the nightly channel.
       https://github.com/taiki-e/const_fn/issues/27
@@ -62,7 +383,10 @@ TODO: document the behavior on the version on the nightly channel.
 
 ## Alternatives
 
-This crate is proc-macro, but is very lightweight, and has no dependencies.
+This crate is proc-macro, but is very lightweight, and has no dependencies
+other than [`const_fn_core`](https://docs.rs/const_fn_core) and
+[`const_fn_build`](https://docs.rs/const_fn_build), small helpers maintained in
+the same repository.
 
 You can manually define declarative macros with similar functionality (see
 [`if_rust_version`](https://github.com/ogoffart/if_rust_version#examples)),
@@ -87,180 +411,223 @@ be maintained manually)
 #[allow(unused_extern_crates)]
 extern crate proc_macro;
 
-#[macro_use]
-mod error;
-
-mod ast;
-mod iter;
-mod to_tokens;
-mod utils;
-
-use std::str::FromStr;
-
-use proc_macro::{Delimiter, TokenStream, TokenTree};
-
-use crate::{
-    ast::LitStr,
-    error::{Error, Result},
-    iter::TokenIter,
-    to_tokens::ToTokens,
-    utils::{cfg_attrs, parse_as_empty, tt_span},
-};
+use proc_macro::TokenStream;
 
 /// A lightweight attribute for easy generation of const functions with conditional compilations.
 ///
 /// See the [crate-level documentation](crate) for details.
 #[proc_macro_attribute]
 pub fn const_fn(args: TokenStream, input: TokenStream) -> TokenStream {
-    expand(args, input).unwrap_or_else(Error::into_compile_error)
+    const_fn_core::const_fn(args, input)
 }
 
-fn expand(args: TokenStream, input: TokenStream) -> Result<TokenStream> {
-    let arg = parse_arg(args)?;
-    let mut func = ast::parse_input(input)?;
-    Ok(match arg {
-        Arg::Cfg(cfg) => {
-            let (mut tokens, cfg_not) = cfg_attrs(cfg);
-            tokens.extend(func.to_token_stream());
-            tokens.extend(cfg_not);
-            func.print_const = false;
-            tokens.extend(func.to_token_stream());
-            tokens
-        }
-        Arg::Feature(feat) => {
-            let (mut tokens, cfg_not) = cfg_attrs(feat);
-            tokens.extend(func.to_token_stream());
-            tokens.extend(cfg_not);
-            func.print_const = false;
-            tokens.extend(func.to_token_stream());
-            tokens
-        }
-        Arg::Version(req) => {
-            if req.major > 1
-                || req.minor + cfg!(const_fn_assume_incomplete_release) as u32 > VERSION.minor
-            {
-                func.print_const = false;
-            }
-            func.to_token_stream()
-        }
-        Arg::Nightly => {
-            func.print_const = VERSION.nightly;
-            func.to_token_stream()
-        }
-        Arg::Always => func.to_token_stream(),
-    })
+/// An attribute that keeps or removes any item depending on the same conditions
+/// as [`#[const_fn]`](macro@const_fn).
+///
+/// ```
+/// use const_fn::cfg_rustc;
+///
+/// // item is kept on specified version and later compiler
+/// #[cfg_rustc("1.61")]
+/// impl<T: Copy> Wrapper<T> {
+///     const fn get(&self) -> T {
+///         self.0
+///     }
+/// }
+///
+/// // item is kept on nightly compiler
+/// #[cfg_rustc(nightly)]
+/// use std::convert::identity;
+///
+/// // same as `#[cfg(feature = "...")]`
+/// # #[cfg(any(/* always false */))]
+/// #[cfg_rustc(feature = "...")]
+/// # struct _Feature;
+/// struct Feature;
+///
+/// struct Wrapper<T>(T);
+/// ```
+///
+/// Version and nightly conditions are evaluated when the macro expands, and
+/// the item is emitted as-is or removed. `cfg(...)`, `feature = "..."`, and
+/// aliases of them are emitted as `#[cfg(...)]`.
+#[proc_macro_attribute]
+pub fn cfg_rustc(args: TokenStream, input: TokenStream) -> TokenStream {
+    const_fn_core::cfg_rustc(args, input)
 }
 
-enum Arg {
-    // `#[const_fn("...")]`
-    Version(VersionReq),
-    // `#[const_fn(nightly)]`
-    Nightly,
-    // `#[const_fn(cfg(...))]`
-    Cfg(TokenStream),
-    // `#[const_fn(feature = "...")]`
-    Feature(TokenStream),
-    // `#[const_fn]`
-    Always,
+/// An attribute for `#[test]` functions that also evaluates the body of the
+/// test at compile time if the given condition holds.
+///
+/// The condition is written in the same way as the argument of
+/// [`#[const_fn]`](macro@const_fn) and is evaluated in the same way. The test
+/// always runs at runtime as a normal test, and if the condition holds, its
+/// body is also evaluated in a const item, so a failed assertion becomes a
+/// compile error.
+///
+/// The body of the test must be const-compatible on compilers where the
+/// condition holds. The test runs at runtime on all supported compilers, but
+/// the compile-time evaluation is only supported on Rust 1.33 and later, where
+/// `let` statements can be used in const contexts. Also, `assert!` can be used
+/// in const contexts since Rust 1.57, and `assert_eq!` cannot be used at all,
+/// so the condition must not hold on older compilers, e.g.,
+/// `#[const_test("1.57")]` for a test using `assert!`.
+///
+/// ```
+/// use const_fn::{const_fn, const_test};
+///
+/// #[const_fn("1.61")]
+/// fn add(x: u8, y: u8) -> u8 {
+///     x + y
+/// }
+///
+/// #[test]
+/// #[const_test("1.61")]
+/// fn test_add() {
+///     assert!(add(1, 2) == 3);
+/// }
+/// ```
+#[allow(clippy::test_attr_in_doctest)]
+#[proc_macro_attribute]
+pub fn const_test(args: TokenStream, input: TokenStream) -> TokenStream {
+    const_fn_core::const_test(args, input)
 }
 
-fn parse_arg(tokens: TokenStream) -> Result<Arg> {
-    let iter = &mut TokenIter::new(tokens);
-
-    let next = iter.next();
-    let next_span = tt_span(next.as_ref());
-    match next {
-        None => return Ok(Arg::Always),
-        Some(TokenTree::Ident(i)) => match &*i.to_string() {
-            "nightly" => {
-                parse_as_empty(iter)?;
-                return Ok(Arg::Nightly);
-            }
-            "cfg" => {
-                return match iter.next().as_ref() {
-                    Some(TokenTree::Group(g)) if g.delimiter() == Delimiter::Parenthesis => {
-                        parse_as_empty(iter)?;
-                        Ok(Arg::Cfg(g.stream()))
-                    }
-                    tt => bail!(tt_span(tt), "expected `(`"),
-                };
-            }
-            "feature" => {
-                let next = iter.next();
-                return match next.as_ref() {
-                    Some(TokenTree::Punct(p)) if p.as_char() == '=' => match iter.next() {
-                        Some(TokenTree::Literal(l)) => {
-                            let l = LitStr::new(l)?;
-                            parse_as_empty(iter)?;
-                            Ok(Arg::Feature(
-                                vec![TokenTree::Ident(i), next.unwrap(), l.token.into()]
-                                    .into_iter()
-                                    .collect(),
-                            ))
-                        }
-                        tt => bail!(tt_span(tt.as_ref()), "expected string literal"),
-                    },
-                    tt => bail!(tt_span(tt), "expected `=`"),
-                };
-            }
-            _ => {}
-        },
-        Some(TokenTree::Literal(l)) => {
-            if let Ok(l) = LitStr::new(l) {
-                parse_as_empty(iter)?;
-                return match l.value().parse::<VersionReq>() {
-                    Ok(req) => Ok(Arg::Version(req)),
-                    Err(e) => bail!(l.span(), "{}", e),
-                };
-            }
-        }
-        Some(_) => {}
-    }
-
-    bail!(next_span, "expected one of: `nightly`, `cfg`, `feature`, string literal")
+/// An attribute for `static` items whose initializer can be evaluated at
+/// compile time only if the given condition holds.
+///
+/// The condition is written in the same way as the argument of
+/// [`#[const_fn]`](macro@const_fn) and is evaluated in the same way. If the
+/// condition holds, the static is emitted as-is. Otherwise, the static is
+/// replaced with a wrapper that evaluates the initializer on first access and
+/// dereferences to the value.
+///
+/// ```
+/// use const_fn::{const_fn, const_or_lazy};
+///
+/// #[const_fn("1.61")]
+/// fn make() -> Option<&'static str> {
+///     Some("value")
+/// }
+///
+/// #[const_or_lazy("1.61")]
+/// static VALUE: Option<&str> = make();
+///
+/// assert_eq!(VALUE.unwrap(), "value");
+/// ```
+///
+/// As the lazily initialized static is a wrapper, access the value through
+/// method calls or deref coercion (e.g., `&VALUE` where `&T` is expected),
+/// which work in both cases. `static mut` is not supported.
+///
+/// The wrapper is defined in [`const_fn_core`](https://docs.rs/const_fn_core),
+/// so crates that use this attribute need to depend on `const_fn_core` as
+/// well. The lazily initialized static requires Rust 1.32, as it is
+/// constructed with `Once::new`, which is `const` since Rust 1.32.
+#[proc_macro_attribute]
+pub fn const_or_lazy(args: TokenStream, input: TokenStream) -> TokenStream {
+    const_fn_core::const_or_lazy(args, input)
 }
 
-struct VersionReq {
-    major: u32,
-    minor: u32,
+/// A function-like macro that selects the first arm whose condition holds.
+///
+/// Each arm is a condition (any argument supported by
+/// [`#[const_fn]`](macro@const_fn)) or `_`, followed by `=>` and a block
+/// of items.
+///
+/// ```
+/// const_fn::version_match! {
+///     "1.83" => {
+///         pub const fn set(x: &mut u8, v: u8) {
+///             *x = v;
+///         }
+///     },
+///     "1.61" => {
+///         pub const fn set(x: &mut u8, v: u8) {
+///             /* workaround */
+/// #           unimplemented!()
+///         }
+///     },
+///     _ => {
+///         pub fn set(x: &mut u8, v: u8) {
+///             *x = v;
+///         }
+///     },
+/// }
+/// ```
+///
+/// Version and nightly conditions are evaluated when the macro expands.
+/// `cfg(...)` and `feature = "..."` conditions are evaluated by the compiler,
+/// so when they are used the arms are emitted with the corresponding
+/// `#[cfg(...)]` via a helper `macro_rules!`; in that case, the macro can only
+/// be used where `macro_rules!` can be defined (e.g., not in impl blocks).
+#[proc_macro]
+pub fn version_match(input: TokenStream) -> TokenStream {
+    const_fn_core::version_match(input)
 }
 
-impl FromStr for VersionReq {
-    type Err = String;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut pieces = s.split('.');
-        let major = pieces
-            .next()
-            .ok_or("need to specify the major version")?
-            .parse::<u32>()
-            .map_err(|e| e.to_string())?;
-        let minor = pieces
-            .next()
-            .ok_or("need to specify the minor version")?
-            .parse::<u32>()
-            .map_err(|e| e.to_string())?;
-        if let Some(s) = pieces.next() {
-            Err(format!("unexpected input: .{}", s))
-        } else {
-            Ok(Self { major, minor })
-        }
-    }
+/// A function-like macro that expands to the version of the compiler detected
+/// by this crate, i.e., the version used to evaluate the conditions of
+/// [`#[const_fn]`](macro@const_fn).
+///
+/// The expanded expression is a tuple literal of type `(u32, u32, u32, bool)`
+/// (usable in const contexts) with the following elements:
+///
+/// - the major version (always `1`)
+/// - the minor version
+/// - the patch version
+/// - whether the compiler is treated as nightly, i.e., whether
+///   `#[const_fn(nightly)]` functions are `const`
+///
+/// ```
+/// const VERSION: (u32, u32, u32, bool) = const_fn::rustc_version!();
+/// const MINOR: u32 = VERSION.1;
+///
+/// let (major, minor, patch, nightly) = const_fn::rustc_version!();
+/// println!(
+///     "compiled with rustc {}.{}.{}{}",
+///     major,
+///     minor,
+///     patch,
+///     if nightly { "-nightly" } else { "" }
+/// );
+/// assert_eq!(major, 1);
+/// ```
+#[proc_macro]
+pub fn rustc_version(input: TokenStream) -> TokenStream {
+    const_fn_core::rustc_version(input)
 }
 
-struct Version {
-    minor: u32,
-    nightly: bool,
+/// A function-like macro that checks that an expression can be evaluated at
+/// compile time if the given condition holds.
+///
+/// The condition is written in the same way as the argument of
+/// [`#[const_fn]`](macro@const_fn) and is evaluated in the same way. If the
+/// condition holds, the expression is evaluated in a const item (i.e., at
+/// compile time), otherwise it is evaluated at runtime.
+///
+/// Note that the expression is only evaluated at runtime when the condition
+/// is false. When the condition holds, it is not run at runtime at all, so this
+/// does not test the behavior of the non-`const` code path (e.g., use normal
+/// assertions alongside this macro for that).
+///
+/// This macro is intended to be used in statement position (e.g., in tests).
+/// As the expression may be evaluated in a const item, it cannot refer to local
+/// variables or generic parameters of the enclosing function. On Rust 1.31, the
+/// value of the expression must not have a destructor if it is evaluated at
+/// compile time.
+///
+/// ```
+/// use const_fn::{assert_const, const_fn};
+///
+/// #[const_fn("1.61")]
+/// fn foo(x: u8) -> Option<u8> {
+///     Some(x)
+/// }
+///
+/// assert_const!("1.61", foo(1));
+/// ```
+#[proc_macro]
+pub fn assert_const(input: TokenStream) -> TokenStream {
+    const_fn_core::assert_const(input)
 }
-
-// Use \ on Windows host to work around https://github.com/rust-lang/rust/issues/75075 / https://github.com/rust-lang/cargo/issues/13919.
-// (Fixed in Rust 1.84: https://github.com/rust-lang/rust/pull/125205)
-#[cfg(const_fn_has_build_script)]
-#[cfg(not(host_os = "windows"))]
-const VERSION: Version = include!(concat!(env!("OUT_DIR"), "/version"));
-#[cfg(const_fn_has_build_script)]
-#[cfg(host_os = "windows")]
-const VERSION: Version = include!(concat!(env!("OUT_DIR"), "\\version"));
-// If build script has not run or unable to determine version, it is considered as our MSRV (Rust 1.31).
-#[cfg(not(const_fn_has_build_script))]
-const VERSION: Version = Version { minor: 31, nightly: false };
@@ -31,6 +31,13 @@ fn main() {
     if is_nightly() {
         println!("cargo:rustc-cfg=const_unstable");
     }
+
+    // Compared with the cfgs above in tests/test.rs.
+    const_fn_build::Config::new()
+        .versions(&["1.31", "1.33", "1.39", "1.46", "1.61", "1.83"])
+        .prefix("const_fn_build_rustc_")
+        .nightly("const_fn_build_nightly")
+        .emit();
 }
 
 fn is_nightly() -> bool {
@@ -0,0 +1,9 @@
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+#![cfg(not(miri))]
+
+#[test]
+fn ui() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/**/*.rs");
+}
@@ -0,0 +1,46 @@
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+#![cfg(not(miri))]
+
+// Builds a crate with `CONST_FN_DEBUG` set and checks the printed traces.
+
+use std::{env, fs, path::Path, process::Command};
+
+#[test]
+fn trace() {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("debug");
+    fs::create_dir_all(dir.join("src")).unwrap();
+    let const_fn = Path::new(env!("CARGO_MANIFEST_DIR")).parent().unwrap();
+    fs::write(
+        dir.join("Cargo.toml"),
+        format!(
+            "[package]\nname = \"debug_fixture\"\nversion = \"0.0.0\"\nedition = \"2018\"\n\n\
+             [dependencies]\nconst_fn = {{ path = {:?} }}\n\n[workspace]\n",
+            const_fn.display().to_string()
+        ),
+    )
+    .unwrap();
+    fs::write(
+        dir.join("src/lib.rs"),
+        "use const_fn::const_fn;\n\n\
+         #[const_fn(\"1.31\")]\npub fn traced() {}\n\n\
+         #[const_fn(\"1.31\")]\npub fn untraced() {}\n",
+    )
+    .unwrap();
+
+    // Use a separate target directory so that const_fn_core is recompiled with
+    // `CONST_FN_DEBUG` without invalidating the main build.
+    let output = Command::new(env::var_os("CARGO").unwrap_or_else(|| "cargo".into()))
+        .args(["build", "--offline", "--quiet"])
+        .current_dir(&dir)
+        .env("CARGO_TARGET_DIR", dir.join("target"))
+        .env("CONST_FN_DEBUG", "traced")
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(output.status.success(), "{}", stderr);
+    assert!(stderr.contains("[const_fn] debug_fixture::traced\n  arg: \"1.31\"\n"), "{}", stderr);
+    assert!(stderr.contains("  decision: const\n"), "{}", stderr);
+    assert!(stderr.contains("  output: pub const fn traced()"), "{}", stderr);
+    assert!(!stderr.contains("untraced"), "{}", stderr);
+}
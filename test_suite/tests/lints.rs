@@ -0,0 +1,63 @@
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+#![cfg(not(miri))]
+
+// Runs clippy on crates with and without `package.metadata.const_fn.allow-lints`.
+
+use std::{env, fs, path::Path, process::Command};
+
+const LIB: &str = "use const_fn::const_fn;
+
+#[const_fn(cfg(any()))]
+pub fn pair() {}
+
+#[const_fn(\"1.31\", check_both)]
+pub fn check_both() {}
+
+/// Docs for foo_bar.
+#[const_fn(cfg(any()), check_both, doc)]
+fn unused() {}
+";
+
+// Returns the stderr of clippy and whether it succeeded.
+fn clippy(name: &str, allow_lints: bool) -> (String, bool) {
+    let target_tmpdir = Path::new(env!("CARGO_TARGET_TMPDIR"));
+    let dir = target_tmpdir.join(name);
+    fs::create_dir_all(dir.join("src")).unwrap();
+    let const_fn = Path::new(env!("CARGO_MANIFEST_DIR")).parent().unwrap();
+    fs::write(
+        dir.join("Cargo.toml"),
+        format!(
+            "[package]\nname = {:?}\nversion = \"0.0.0\"\nedition = \"2018\"\n\n\
+             [package.metadata.const_fn]\nallow-lints = {}\n\n\
+             [dependencies]\nconst_fn = {{ path = {:?} }}\n\n[workspace]\n",
+            name,
+            allow_lints,
+            const_fn.display().to_string()
+        ),
+    )
+    .unwrap();
+    fs::write(dir.join("src/lib.rs"), LIB).unwrap();
+
+    let output = Command::new(env::var_os("CARGO").unwrap_or_else(|| "cargo".into()))
+        .args(["clippy", "--offline", "--quiet", "--", "-D", "clippy::missing_const_for_fn"])
+        .args(["-W", "dead_code", "-W", "clippy::doc_markdown"])
+        .current_dir(&dir)
+        .env("CARGO_TARGET_DIR", target_tmpdir.join("lints-target"))
+        .output()
+        .unwrap();
+    (String::from_utf8(output.stderr).unwrap(), output.status.success())
+}
+
+#[test]
+fn allow_lints() {
+    let (stderr, success) = clippy("lints_allow", true);
+    assert!(success, "{}", stderr);
+    // The lints of the function are reported once, not for each variant.
+    assert_eq!(stderr.matches("function `unused` is never used").count(), 1, "{stderr}");
+    assert_eq!(stderr.matches("item in documentation is missing backticks").count(), 1, "{stderr}");
+
+    let (stderr, success) = clippy("lints_deny", false);
+    assert!(!success, "{}", stderr);
+    assert!(stderr.contains("this could be a `const fn`"), "{}", stderr);
+}
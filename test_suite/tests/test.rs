@@ -112,7 +112,7 @@ pub mod min_const_generics {
 }
 
 pub mod version {
-    use const_fn::const_fn;
+    use const_fn::{assert_const, const_fn};
 
     // min_const_fn (1.31+)
 
@@ -170,6 +170,12 @@ pub mod version {
 
     #[test]
     fn test() {
+        assert_const!("1.31", const_min("min_const_fn"));
+        assert_const!("1.33", const_let("const_let"));
+        assert_const!("1.39", const_vec_new::<u8>());
+        assert_const!("1.46", const_match(1));
+        assert_const!("1.61", A::const_fn_trait_bound(const_vec_new::<u8>()));
+
         assert!(const_min("variables") == "variables");
         assert_eq!(const_let("variables"), "variables");
         assert_eq!(const_vec_new::<u8>(), vec![]);
@@ -179,7 +185,7 @@ pub mod version {
 }
 
 pub mod cfg {
-    use const_fn::const_fn;
+    use const_fn::{assert_const, const_fn};
 
     // min_const_fn (1.31+)
 
@@ -237,6 +243,12 @@ pub mod cfg {
 
     #[test]
     fn test() {
+        assert_const!(cfg(rustc_1_31), const_min("min_const_fn"));
+        assert_const!(cfg(rustc_1_33), const_let("const_let"));
+        assert_const!(cfg(rustc_1_39), const_vec_new::<u8>());
+        assert_const!(cfg(rustc_1_46), const_match(1));
+        assert_const!(cfg(rustc_1_61), A::const_fn_trait_bound(const_vec_new::<u8>()));
+
         assert!(const_min("variables") == "variables");
         assert_eq!(const_let("variables"), "variables");
         assert_eq!(const_vec_new::<u8>(), vec![]);
@@ -260,3 +272,407 @@ pub mod macros {
     args!("1.31");
     const _: () = args(());
 }
+
+pub mod alias {
+    use const_fn::{assert_const, const_fn};
+
+    // See [package.metadata.const_fn.aliases] table in test_suite/Cargo.toml.
+
+    #[const_fn(alias = "min_const_fn")]
+    const fn const_min<T>(x: T) -> T {
+        x
+    }
+    const _CONST_MIN: &str = const_min("min_const_fn");
+
+    #[const_fn(alias = "const_match")]
+    const fn const_match(x: u8) -> Option<u8> {
+        match x {
+            0 => None,
+            x => Some(x),
+        }
+    }
+    #[rustversion::since(1.46)]
+    const _: Option<u8> = const_match(1);
+
+    #[const_fn(alias = "always_false")]
+    const fn always_false() -> String {
+        String::from("always_false")
+    }
+
+    #[test]
+    fn test() {
+        assert_const!(alias = "min_const_fn", const_min("min_const_fn"));
+        assert_const!(alias = "const_match", const_match(1));
+        assert_const!(alias = "always_false", always_false());
+
+        assert!(const_min("variables") == "variables");
+        assert_eq!(const_match(1), Some(1));
+        assert_eq!(always_false(), "always_false");
+    }
+}
+
+pub mod cfg_rustc {
+    use const_fn::cfg_rustc;
+
+    pub struct A<T>(T);
+
+    #[cfg_rustc("1.61")]
+    impl<T: Copy> A<T> {
+        const fn get(&self) -> T {
+            self.0
+        }
+    }
+    #[cfg_rustc(cfg(not(rustc_1_61)))]
+    impl<T: Copy> A<T> {
+        fn get(&self) -> T {
+            self.0
+        }
+    }
+
+    #[cfg_rustc("1.31")]
+    fn version() -> u8 {
+        1
+    }
+    #[cfg_rustc("2.0")]
+    fn version() -> u8 {
+        2
+    }
+
+    #[cfg_rustc(nightly)]
+    const NIGHTLY: bool = true;
+    #[cfg_rustc(cfg(not(const_unstable)))]
+    const NIGHTLY: bool = false;
+
+    #[cfg_rustc(alias = "always_false")]
+    use std::this_module_does_not_exist;
+
+    #[test]
+    fn test() {
+        assert_eq!(A(1).get(), 1);
+        assert_eq!(version(), 1);
+        assert_eq!(NIGHTLY, cfg!(const_unstable));
+    }
+}
+
+pub mod version_match {
+    const_fn::version_match! {
+        "1.83" => {
+            const fn set(x: &mut u8, v: u8) {
+                *x = v;
+            }
+            const VERSION: u8 = 83;
+        },
+        "1.61" => {
+            fn set(x: &mut u8, v: u8) {
+                *x = v;
+            }
+            const VERSION: u8 = 61;
+        },
+        _ => {
+            fn set(x: &mut u8, v: u8) {
+                *x = v;
+            }
+            const VERSION: u8 = 0;
+        }
+    }
+
+    const_fn::version_match! {
+        alias = "always_false" => {
+            const CFG: u8 = 0;
+        },
+        "2.0" => {
+            const CFG: u8 = 2;
+        },
+        cfg(rustc_1_46) => {
+            const CFG: u8 = 46;
+        },
+        cfg(rustc_1_31) => {
+            const CFG: u8 = 31;
+        },
+        _ => {
+            const CFG: u8 = 1;
+        },
+    }
+
+    pub struct S;
+
+    impl S {
+        const_fn::version_match! {
+            "2.0" => {
+                fn f() -> u8 {
+                    2
+                }
+            }
+            "1.31" => {
+                const fn f() -> u8 {
+                    1
+                }
+            }
+        }
+    }
+    const _: u8 = S::f();
+
+    #[test]
+    fn test() {
+        let expected = if cfg!(rustc_1_83) {
+            83
+        } else if cfg!(rustc_1_61) {
+            61
+        } else {
+            0
+        };
+        assert_eq!(VERSION, expected);
+        let mut x = 0;
+        set(&mut x, 1);
+        assert_eq!(x, 1);
+        assert_eq!(CFG, if cfg!(rustc_1_46) { 46 } else { 31 });
+        assert_eq!(S::f(), 1);
+    }
+}
+
+pub mod rustc_version {
+    const VERSION: (u32, u32, u32, bool) = const_fn::rustc_version!();
+
+    #[test]
+    fn test() {
+        let (major, minor, _patch, nightly) = const_fn::rustc_version!();
+        assert_eq!(major, 1);
+        assert_eq!(minor, VERSION.1);
+        assert_eq!(minor >= 61, cfg!(rustc_1_61));
+        assert_eq!(minor >= 83, cfg!(rustc_1_83));
+        assert_eq!(nightly, cfg!(const_unstable));
+        assert_eq!(const_fn::rustc_version!(), VERSION);
+    }
+}
+
+pub mod export_flag {
+    use const_fn::const_fn;
+
+    #[const_fn("1.61", export_flag)]
+    pub fn version() {}
+
+    #[const_fn(cfg(rustc_1_83), export_flag)]
+    pub(crate) fn cfg() {}
+
+    #[const_fn(nightly, export_flag)]
+    fn r#nightly() {}
+
+    pub struct S;
+
+    impl S {
+        #[const_fn("1.83", export_flag)]
+        pub fn method() {}
+    }
+
+    #[test]
+    fn test() {
+        version();
+        cfg();
+        nightly();
+        S::method();
+        assert_eq!(__CONST_FN_IS_CONST_version, cfg!(rustc_1_61));
+        assert_eq!(__CONST_FN_IS_CONST_cfg, cfg!(rustc_1_83));
+        assert_eq!(__CONST_FN_IS_CONST_nightly, cfg!(const_unstable));
+        assert_eq!(S::__CONST_FN_IS_CONST_method, cfg!(rustc_1_83));
+    }
+}
+
+#[deny(missing_docs)]
+pub mod doc {
+    //! Functions with the `doc` option. See also ui/const_fn/doc-missing-docs.rs.
+
+    use const_fn::const_fn;
+
+    /// Docs.
+    #[const_fn("1.61", doc)]
+    #[must_use]
+    pub fn version() -> u8 {
+        1
+    }
+
+    /// Docs.
+    ///
+    /// More docs.
+    #[const_fn(cfg(rustc_1_83), doc)]
+    #[must_use]
+    pub fn cfg() -> u8 {
+        2
+    }
+
+    #[const_fn(nightly, doc)]
+    fn nightly() -> u8 {
+        3
+    }
+
+    #[cfg(rustc_1_61)]
+    const _VERSION: u8 = version();
+    #[cfg(rustc_1_83)]
+    const _CFG: u8 = cfg();
+
+    #[test]
+    fn test() {
+        // `cfg(doc)` only affects rustdoc; the actual functions are unchanged.
+        assert_eq!(version(), 1);
+        assert_eq!(cfg(), 2);
+        assert_eq!(nightly(), 3);
+    }
+}
+
+pub mod check_both {
+    use const_fn::const_fn;
+
+    #[const_fn(cfg(rustc_1_61), check_both)]
+    fn free(x: u8) -> u8 {
+        if x == 0 { 0 } else { free(x - 1) + 1 }
+    }
+
+    #[const_fn(cfg(not(rustc_1_31)), check_both)]
+    #[allow(clippy::trivially_copy_pass_by_ref)]
+    fn inactive(x: &u8) -> u8 {
+        *x
+    }
+
+    #[const_fn("1.61", check_both)]
+    fn version(x: u8) -> u8 {
+        x
+    }
+
+    #[const_fn(nightly, check_both)]
+    fn nightly(x: u8) -> u8 {
+        x
+    }
+
+    pub struct S(u8);
+
+    impl S {
+        #[const_fn(cfg(rustc_1_61), check_both)]
+        fn get(&self) -> u8 {
+            self.0
+        }
+    }
+
+    #[test]
+    fn test() {
+        assert_eq!(free(3), 3);
+        assert_eq!(inactive(&1), 1);
+        assert_eq!(version(1), 1);
+        assert_eq!(nightly(1), 1);
+        assert_eq!(S(2).get(), 2);
+    }
+}
+
+pub mod with_attrs {
+    use const_fn::const_fn;
+
+    // The `without` attribute would remove the const variant.
+    #[const_fn("1.31", with(#[cfg(all())], #[must_use]), without(#[cfg(any())]))]
+    const fn const_variant() -> u8 {
+        1
+    }
+    const _: u8 = const_variant();
+
+    // The `with` attribute would remove the non-const variant.
+    #[const_fn(cfg(any()), with(#[cfg(any())]), without(#[inline],))]
+    const fn non_const_variant() -> u8 {
+        2
+    }
+
+    #[test]
+    fn test() {
+        assert_eq!(const_variant(), 1);
+        assert_eq!(non_const_variant(), 2);
+    }
+}
+
+pub mod const_test {
+    use const_fn::{const_fn, const_test};
+
+    #[const_fn("1.46")]
+    const fn const_match(x: u8) -> Option<u8> {
+        match x {
+            0 => None,
+            x => Some(x),
+        }
+    }
+
+    #[const_test("1.57")]
+    #[test]
+    fn version() {
+        assert!(const_match(0).is_none());
+    }
+
+    #[test]
+    #[const_test(cfg(rustc_1_61))]
+    fn cfg() {
+        let x = const_match(1);
+        assert!(matches!(x, Some(1)));
+    }
+
+    #[test]
+    #[const_test(cfg(any(/* always false */)))]
+    fn never() {
+        let v = String::from("never");
+        assert_eq!(v.len(), 5);
+    }
+}
+
+// The lazily initialized statics do not put unsafe code in this crate.
+#[forbid(unsafe_code)]
+pub mod const_or_lazy {
+    use std::collections::HashMap;
+
+    use const_fn::{const_fn, const_or_lazy};
+
+    #[allow(clippy::unnecessary_wraps)]
+    #[const_fn("1.61")]
+    const fn make<T>(x: T) -> Option<T> {
+        Some(x)
+    }
+
+    #[const_or_lazy("1.61")]
+    static VERSION: Option<&str> = make("version");
+
+    #[const_or_lazy(cfg(rustc_1_83))]
+    pub(crate) static CFG: Option<u8> = make(1);
+
+    /// Docs.
+    #[const_or_lazy(cfg(any(/* always false */)))]
+    pub static LAZY: HashMap<&str, Box<dyn Fn() -> u8 + Sync + Send>> = {
+        let mut map = HashMap::new();
+        map.insert("lazy", Box::new(|| 2_u8) as Box<dyn Fn() -> u8 + Sync + Send>);
+        map
+    };
+
+    #[const_or_lazy("1.999")]
+    static ITER: Option<Box<dyn Iterator<Item = &u8> + Sync>> = None;
+
+    fn id(s: &str) -> &str {
+        s
+    }
+    #[const_or_lazy("1.999")]
+    static FN: (fn(&str) -> &str, &'_ [u8]) = (id, &[]);
+
+    #[test]
+    fn test() {
+        assert_eq!(VERSION.unwrap(), "version");
+        assert_eq!(CFG.unwrap(), 1);
+        assert_eq!(LAZY["lazy"](), 2);
+        assert_eq!(LAZY.len(), 1);
+        assert!(ITER.is_none());
+        assert_eq!((FN.0)("fn"), "fn");
+    }
+}
+
+pub mod const_fn_build {
+    #[test]
+    fn test() {
+        // The cfgs emitted by const_fn_build match those detected by autocfg.
+        assert_eq!(cfg!(const_fn_build_rustc_1_31), cfg!(rustc_1_31));
+        assert_eq!(cfg!(const_fn_build_rustc_1_33), cfg!(rustc_1_33));
+        assert_eq!(cfg!(const_fn_build_rustc_1_39), cfg!(rustc_1_39));
+        assert_eq!(cfg!(const_fn_build_rustc_1_46), cfg!(rustc_1_46));
+        assert_eq!(cfg!(const_fn_build_rustc_1_61), cfg!(rustc_1_61));
+        assert_eq!(cfg!(const_fn_build_rustc_1_83), cfg!(rustc_1_83));
+        assert_eq!(cfg!(const_fn_build_nightly), cfg!(const_unstable));
+    }
+}
@@ -0,0 +1,14 @@
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+use const_fn::assert_const;
+
+fn not_const() -> u8 {
+    0
+}
+
+fn main() {
+    assert_const!("1.61" not_const());
+    assert_const!("1.61",);
+    assert_const!(not_const());
+    assert_const!("1.31", not_const());
+}
@@ -0,0 +1,19 @@
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! `check_both` type-checks the variant that is not compiled.
+
+use const_fn::const_fn;
+
+// The const variant is inactive, but its copy is compiled.
+#[const_fn(cfg(any()), check_both)]
+pub fn non_const_body() -> Vec<u8> {
+    let mut v = Vec::new();
+    v.push(1);
+    v
+}
+
+// The attributes of the inactive non-const variant are carried over to its copy.
+#[const_fn(cfg(all()), check_both, without(#[inline(invalid)]))]
+pub fn non_const_attrs() {}
+
+fn main() {}
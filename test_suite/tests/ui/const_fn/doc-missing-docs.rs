@@ -0,0 +1,16 @@
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+//! The `doc` option does not add documentation to undocumented functions.
+
+#![deny(missing_docs)]
+
+use const_fn::const_fn;
+
+/// Docs.
+#[const_fn("1.61", doc)]
+pub fn documented() {}
+
+#[const_fn("1.61", doc)]
+pub fn undocumented() {}
+
+fn main() {}
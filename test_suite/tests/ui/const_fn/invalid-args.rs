@@ -0,0 +1,35 @@
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+use const_fn::const_fn;
+
+#[const_fn(foo)]
+fn unknown() {}
+
+#[const_fn(1.61)]
+fn number() {}
+
+#[const_fn("1.x")]
+fn invalid_version() {}
+
+#[const_fn("1")]
+fn no_minor() {}
+
+#[const_fn(cfg)]
+fn cfg_without_paren() {}
+
+#[const_fn(cfg[a])]
+fn cfg_bracket() {}
+
+#[const_fn(feature)]
+fn feature_without_eq() {}
+
+#[const_fn(feature = x)]
+fn feature_ident() {}
+
+#[const_fn(feature = b"x")]
+fn feature_byte_str() {}
+
+#[const_fn(alias = "unknown")]
+fn unknown_alias() {}
+
+fn main() {}
@@ -0,0 +1,14 @@
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+use const_fn::const_fn;
+
+#[const_fn]
+struct A;
+
+#[const_fn("1.61")]
+pub(crate) enum B {}
+
+#[const_fn(nightly)]
+static C: u8 = 0;
+
+fn main() {}
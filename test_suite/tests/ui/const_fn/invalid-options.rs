@@ -0,0 +1,26 @@
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+use const_fn::const_fn;
+
+#[const_fn(nightly, foo)]
+fn unknown() {}
+
+#[const_fn(nightly, export_flag, export_flag)]
+fn duplicate() {}
+
+#[const_fn(nightly export_flag)]
+fn missing_comma() {}
+
+#[const_fn(nightly, "1.61")]
+fn not_option() {}
+
+#[const_fn(nightly, with)]
+fn with_without_list() {}
+
+#[const_fn(nightly, with(inline))]
+fn with_not_attribute() {}
+
+#[const_fn(nightly, without(#[inline] #[cold]))]
+fn without_missing_comma() {}
+
+fn main() {}
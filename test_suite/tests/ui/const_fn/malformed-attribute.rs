@@ -0,0 +1,8 @@
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+use const_fn::const_fn;
+
+#[const_fn = "1.61"]
+fn name_value() {}
+
+fn main() {}
@@ -0,0 +1,20 @@
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+use const_fn::const_or_lazy;
+
+#[const_or_lazy("1.61")]
+fn a() {}
+
+#[const_or_lazy("1.61")]
+pub(crate) struct B;
+
+#[const_or_lazy("1.61")]
+static mut C: u8 = 0;
+
+#[const_or_lazy("1.61")]
+static D: u8;
+
+#[const_or_lazy(foo)]
+static E: u8 = 0;
+
+fn main() {}
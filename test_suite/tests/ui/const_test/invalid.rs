@@ -0,0 +1,11 @@
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+use const_fn::const_test;
+
+#[const_test]
+struct A;
+
+#[const_test(foo)]
+fn unknown() {}
+
+fn main() {}
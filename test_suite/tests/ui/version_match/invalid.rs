@@ -0,0 +1,28 @@
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+
+use const_fn::version_match;
+
+version_match! {}
+
+version_match! {
+    "1.61" { fn a() {} }
+}
+
+version_match! {
+    _ => { fn b() {} }
+    "1.61" => { fn b() {} }
+}
+
+version_match! {
+    => { fn c() {} }
+}
+
+version_match! {
+    "1.61" => fn d() {}
+}
+
+version_match! {
+    1.61 => { fn e() {} }
+}
+
+fn main() {}